@@ -4,136 +4,495 @@ extern crate proc_macro;
 
 use crate::proc_macro::TokenStream;
 use quote::quote;
-use syn;
+use syn::ext::IdentExt;
 
-#[proc_macro_derive(MessagePackFrom)]
+#[proc_macro_derive(MessagePackFrom, attributes(msgpack))]
 pub fn message_pack_from_macro_derive(input: TokenStream) -> TokenStream {
     let ast = syn::parse(input).unwrap();
-    impl_message_pack_from_macro(&ast)
+    impl_message_pack_from_macro(&ast).unwrap_or_else(|e| e.to_compile_error().into())
 }
 
-fn impl_message_pack_from_macro(ast: &syn::DeriveInput) -> TokenStream {
-    let name = &ast.ident;
-    let gen = quote! {
-        impl<T: Into<#name>> From<Option<T>> for #name {
-            fn from(value: Option<T>) -> Self {
-                value.map_or(Self::Nil, Into::into)
-            }
-        }
+/// MessagePack types a variant can stand for, with the variant name that takes the role when no `#[msgpack(...)]` is given.
+const ROLES: [(&str, &str); 18] = [
+    ("nil", "Nil"),
+    ("bool", "Bool"),
+    ("float32", "Float32"),
+    ("float64", "Float64"),
+    ("uint8", "UInt8"),
+    ("uint16", "UInt16"),
+    ("uint32", "UInt32"),
+    ("uint64", "UInt64"),
+    ("int8", "Int8"),
+    ("int16", "Int16"),
+    ("int32", "Int32"),
+    ("int64", "Int64"),
+    ("binary", "Binary"),
+    ("string", "String"),
+    ("array", "Array"),
+    ("map", "Map"),
+    ("extension", "Extension"),
+    ("timestamp", "Timestamp"),
+];
 
-        impl From<bool> for #name {
-            fn from(value: bool) -> Self {
-                Self::Bool(value)
-            }
-        }
+/// Pairs every variant with its role. Variants without a role are extra variants.
+fn variant_roles(ast: &syn::DeriveInput) -> syn::Result<Vec<(&syn::Variant, Option<&'static str>)>> {
+    let variants = match &ast.data {
+        syn::Data::Enum(data) => &data.variants,
+        _ => return Err(syn::Error::new_spanned(&ast.ident, "MessagePack values can only be derived for enums")),
+    };
 
-        impl From<f32> for #name {
-            fn from(value: f32) -> Self {
-                Self::Float32(value)
+    let mut result = Vec::new();
+    let mut seen: Vec<(&str, &syn::Variant)> = Vec::new();
+    for variant in variants {
+        let mut role = None;
+        for attr in variant.attrs.iter().filter(|attr| attr.path.is_ident("msgpack")) {
+            let ident: syn::Ident = attr.parse_args()?;
+            let name = ROLES.iter().find(|(role, _)| ident == role).ok_or_else(|| syn::Error::new(ident.span(), "unknown role"))?.0;
+            if role.is_some() {
+                return Err(syn::Error::new_spanned(attr, "variant already has a role"));
             }
+            role = Some(name);
         }
-
-        impl From<f64> for #name {
-            fn from(value: f64) -> Self {
-                Self::Float64(value)
-            }
+        if role.is_none() {
+            role = ROLES.iter().find(|(_, default)| variant.ident == default).map(|(role, _)| *role);
         }
-
-        impl From<u8> for #name {
-            fn from(value: u8) -> Self {
-                Self::UInt8(value)
+        if let Some(role) = role {
+            match (&variant.fields, role) {
+                (syn::Fields::Unit, "nil") => {},
+                (syn::Fields::Unnamed(fields), role) if role != "nil" && fields.unnamed.len() == 1 => {},
+                (_, "nil") => return Err(syn::Error::new_spanned(variant, "role `nil` needs a unit variant")),
+                (_, role) => return Err(syn::Error::new_spanned(variant, format!("role `{}` needs a variant with exactly one unnamed field", role))),
             }
-        }
-
-        impl From<u16> for #name {
-            fn from(value: u16) -> Self {
-                Self::UInt16(value)
+            if let Some((_, other)) = seen.iter().find(|(seen, _)| *seen == role) {
+                return Err(syn::Error::new_spanned(variant, format!("duplicate role `{}`, already taken by `{}`", role, other.ident)));
             }
+            seen.push((role, variant));
         }
+        result.push((variant, role));
+    }
 
-        impl From<u32> for #name {
-            fn from(value: u32) -> Self {
-                Self::UInt32(value)
-            }
-        }
+    let missing: Vec<&str> = ROLES.iter().map(|(role, _)| *role).filter(|role| !seen.iter().any(|(seen, _)| seen == role)).collect();
+    if !missing.is_empty() {
+        return Err(syn::Error::new_spanned(&ast.ident, format!("missing variants for roles: {}; mark them with #[msgpack(...)]", missing.join(", "))));
+    }
+    Ok(result)
+}
 
-        impl From<u64> for #name {
-            fn from(value: u64) -> Self {
-                Self::UInt64(value)
-            }
+fn field_type(variant: &syn::Variant) -> &syn::Type {
+    &variant.fields.iter().next().expect("checked by variant_roles").ty
+}
+
+/// Returns the type arguments of `ty` if its last path segment is one of `collections`, such as `Vec`.
+fn type_arguments<'a>(ty: &'a syn::Type, collections: &[&str]) -> Option<Vec<&'a syn::Type>> {
+    let segment = match ty {
+        syn::Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if collections.iter().any(|collection| segment.ident == collection) => args.args.iter().map(|arg| match arg {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }).collect(),
+        _ => None,
+    }
+}
+
+/// Replaces a bare `Self`, which is not allowed in impl generics, with the enum type.
+fn replace_self(ty: &syn::Type, name: &syn::Ident, ty_generics: &syn::TypeGenerics) -> proc_macro2::TokenStream {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() && path.path.is_ident("Self") => quote! { #name #ty_generics },
+        _ => quote! { #ty },
+    }
+}
+
+fn impl_message_pack_from_macro(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let mut impls = Vec::new();
+    for (variant, role) in variant_roles(ast)? {
+        let ident = &variant.ident;
+        let role = match role {
+            Some(role) => role,
+            None => continue,
+        };
+        if role == "nil" {
+            let mut generics = ast.generics.clone();
+            generics.params.push(syn::parse_quote!(__T: ::std::convert::Into<#name #ty_generics>));
+            let (option_impl_generics, _, _) = generics.split_for_impl();
+            impls.push(quote! {
+                impl #option_impl_generics ::std::convert::From<::std::option::Option<__T>> for #name #ty_generics #where_clause {
+                    fn from(value: ::std::option::Option<__T>) -> Self {
+                        value.map_or(Self::#ident, ::std::convert::Into::into)
+                    }
+                }
+            });
+            continue;
         }
 
-        impl From<i8> for #name {
-            fn from(value: i8) -> Self {
-                Self::Int8(value)
-            }
+        let ty = field_type(variant);
+        match type_arguments(ty, &["BTreeMap", "HashMap"]) {
+            Some(args) if role == "map" && args.len() == 2 => {
+                let key = replace_self(args[0], name, &ty_generics);
+                let value = replace_self(args[1], name, &ty_generics);
+                let mut btree_map_generics = ast.generics.clone();
+                btree_map_generics.params.push(syn::parse_quote!(__K: ::std::convert::Into<#key>));
+                btree_map_generics.params.push(syn::parse_quote!(__V: ::std::convert::Into<#value>));
+                let mut hash_map_generics = btree_map_generics.clone();
+                hash_map_generics.params.push(syn::parse_quote!(__S));
+                let (btree_map_impl_generics, _, _) = btree_map_generics.split_for_impl();
+                let (hash_map_impl_generics, _, _) = hash_map_generics.split_for_impl();
+                impls.push(quote! {
+                    impl #btree_map_impl_generics ::std::convert::From<::std::collections::BTreeMap<__K, __V>> for #name #ty_generics #where_clause {
+                        fn from(value: ::std::collections::BTreeMap<__K, __V>) -> Self {
+                            Self::#ident(value.into_iter().map(|(k, v)| (::std::convert::Into::into(k), ::std::convert::Into::into(v))).collect())
+                        }
+                    }
+
+                    impl #hash_map_impl_generics ::std::convert::From<::std::collections::HashMap<__K, __V, __S>> for #name #ty_generics #where_clause {
+                        fn from(value: ::std::collections::HashMap<__K, __V, __S>) -> Self {
+                            Self::#ident(value.into_iter().map(|(k, v)| (::std::convert::Into::into(k), ::std::convert::Into::into(v))).collect())
+                        }
+                    }
+                });
+            },
+            _ => match type_arguments(ty, &["Vec"]) {
+                Some(args) if role == "array" && args.len() == 1 => {
+                    let element = args[0];
+                    let bound = replace_self(element, name, &ty_generics);
+                    let mut vec_generics = ast.generics.clone();
+                    vec_generics.params.push(syn::parse_quote!(__T: ::std::convert::Into<#bound>));
+                    let (vec_impl_generics, _, _) = vec_generics.split_for_impl();
+                    let mut slice_generics = ast.generics.clone();
+                    slice_generics.make_where_clause().predicates.push(syn::parse_quote!(#bound: ::std::clone::Clone));
+                    let (_, _, slice_where_clause) = slice_generics.split_for_impl();
+                    impls.push(quote! {
+                        impl #vec_impl_generics ::std::convert::From<::std::vec::Vec<__T>> for #name #ty_generics #where_clause {
+                            fn from(value: ::std::vec::Vec<__T>) -> Self {
+                                Self::#ident(value.into_iter().map(::std::convert::Into::into).collect())
+                            }
+                        }
+
+                        impl #impl_generics ::std::convert::From<&[#element]> for #name #ty_generics #slice_where_clause {
+                            fn from(value: &[#element]) -> Self {
+                                Self::#ident(::std::vec::Vec::from(value))
+                            }
+                        }
+                    });
+                },
+                _ => impls.push(quote! {
+                    impl #impl_generics ::std::convert::From<#ty> for #name #ty_generics #where_clause {
+                        fn from(value: #ty) -> Self {
+                            Self::#ident(value)
+                        }
+                    }
+                }),
+            },
+        }
+        if role == "string" {
+            impls.push(quote! {
+                impl #impl_generics ::std::convert::From<&str> for #name #ty_generics #where_clause {
+                    fn from(value: &str) -> Self {
+                        Self::#ident(::std::convert::From::from(value))
+                    }
+                }
+            });
         }
+    }
+    let gen = quote! {
+        #(#impls)*
+    };
+    Ok(gen.into())
+}
 
-        impl From<i16> for #name {
-            fn from(value: i16) -> Self {
-                Self::Int16(value)
+#[proc_macro_derive(MessagePackExtension, attributes(msgpack_ext))]
+pub fn message_pack_extension_macro_derive(input: TokenStream) -> TokenStream {
+    let ast = syn::parse(input).unwrap();
+    impl_message_pack_extension_macro(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+struct ExtensionAttributes {
+    t: Option<i8>,
+    layout: Option<syn::LitStr>,
+}
+
+fn parse_extension_attributes(attrs: &[syn::Attribute]) -> syn::Result<ExtensionAttributes> {
+    let mut t = None;
+    let mut layout = None;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("msgpack_ext")) {
+        attr.parse_args_with(|input: syn::parse::ParseStream| {
+            while !input.is_empty() {
+                let key = input.call(syn::Ident::parse_any)?;
+                input.parse::<syn::Token![=]>()?;
+                if key == "type" {
+                    let lit = input.parse::<syn::LitInt>()?;
+                    match lit.base10_parse::<i8>() {
+                        Ok(v) if v >= 0 => t = Some(v),
+                        _ => return Err(syn::Error::new(lit.span(), "extension type must be between 0 and 127")),
+                    }
+                } else if key == "layout" {
+                    layout = Some(input.parse::<syn::LitStr>()?);
+                } else {
+                    return Err(syn::Error::new(key.span(), "unknown msgpack_ext attribute"));
+                }
+                if !input.is_empty() {
+                    input.parse::<syn::Token![,]>()?;
+                }
             }
+            Ok(())
+        })?;
+    }
+    Ok(ExtensionAttributes { t, layout })
+}
+
+fn impl_message_pack_extension_macro(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let ExtensionAttributes { t, layout } = parse_extension_attributes(&ast.attrs)?;
+    let t = t.ok_or_else(|| syn::Error::new_spanned(name, "missing #[msgpack_ext(type = ...)]"))?;
+    let msgpack_layout = match &layout {
+        None => false,
+        Some(lit) if lit.value() == "fixed" => false,
+        Some(lit) if lit.value() == "msgpack" => true,
+        Some(lit) => return Err(syn::Error::new(lit.span(), "layout must be \"fixed\" or \"msgpack\"")),
+    };
+    let fields = match &ast.data {
+        syn::Data::Struct(data) => &data.fields,
+        _ => return Err(syn::Error::new_spanned(name, "MessagePackExtension can only be derived for structs")),
+    };
+    let members: Vec<syn::Member> = fields.iter().enumerate().map(|(i, field)| match &field.ident {
+        Some(ident) => syn::Member::Named(ident.clone()),
+        None => syn::Member::Unnamed(syn::Index::from(i)),
+    }).collect();
+    let len = members.len();
+
+    let (pack, unpack, reader, header, rest) = if msgpack_layout {
+        (
+            quote! { pack_msgpack },
+            quote! { unpack_msgpack },
+            quote! { ::messagepack_rs::reader::Reader::new(&extension.data[..]) },
+            (
+                quote! { ::messagepack_rs::extension::field::pack_msgpack_fields_len(#len, &mut data)?; },
+                quote! {
+                    if reader.read_array_len()? != #len {
+                        return ::std::result::Result::Err(::messagepack_rs::deserializable::DeserializeError::InvalidLength);
+                    }
+                },
+            ),
+            quote! { reader.get_ref() },
+        )
+    } else {
+        (quote! { pack_fixed }, quote! { unpack_fixed }, quote! { &extension.data[..] }, (quote! {}, quote! {}), quote! { reader })
+    };
+    let (pack_header, unpack_header) = header;
+    let construct = match fields {
+        syn::Fields::Named(_) => quote! {
+            Self { #(#members: ::messagepack_rs::extension::field::ExtensionField::#unpack(&mut reader)?,)* }
+        },
+        syn::Fields::Unnamed(_) => {
+            let unpacks = members.iter().map(|_| quote! { ::messagepack_rs::extension::field::ExtensionField::#unpack(&mut reader)? });
+            quote! { Self(#(#unpacks,)*) }
+        },
+        syn::Fields::Unit => quote! { Self },
+    };
+
+    let gen = quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            pub const EXTENSION_TYPE: i8 = #t;
         }
 
-        impl From<i32> for #name {
-            fn from(value: i32) -> Self {
-                Self::Int32(value)
+        impl #impl_generics ::std::convert::TryFrom<&#name #ty_generics> for ::messagepack_rs::extension::Extension #where_clause {
+            type Error = ::messagepack_rs::serializable::SerializeError;
+
+            fn try_from(value: &#name #ty_generics) -> ::std::result::Result<Self, Self::Error> {
+                let mut data = ::std::vec::Vec::new();
+                #pack_header
+                #(::messagepack_rs::extension::field::ExtensionField::#pack(&value.#members, &mut data)?;)*
+                ::std::result::Result::Ok(::messagepack_rs::extension::Extension { t: #t, data })
             }
         }
 
-        impl From<i64> for #name {
-            fn from(value: i64) -> Self {
-                Self::Int64(value)
+        impl #impl_generics ::std::convert::TryFrom<#name #ty_generics> for ::messagepack_rs::extension::Extension #where_clause {
+            type Error = ::messagepack_rs::serializable::SerializeError;
+
+            fn try_from(value: #name #ty_generics) -> ::std::result::Result<Self, Self::Error> {
+                ::std::convert::TryFrom::try_from(&value)
             }
         }
 
-        impl From<String> for #name {
-            fn from(value: String) -> Self {
-                Self::String(value)
+        impl #impl_generics ::std::convert::TryFrom<&::messagepack_rs::extension::Extension> for #name #ty_generics #where_clause {
+            type Error = ::messagepack_rs::deserializable::DeserializeError;
+
+            fn try_from(extension: &::messagepack_rs::extension::Extension) -> ::std::result::Result<Self, Self::Error> {
+                if extension.t != #t {
+                    return ::std::result::Result::Err(::messagepack_rs::deserializable::DeserializeError::InvalidValue);
+                }
+                let mut reader = #reader;
+                #unpack_header
+                let value = #construct;
+                if !#rest.is_empty() {
+                    return ::std::result::Result::Err(::messagepack_rs::deserializable::DeserializeError::InvalidLength);
+                }
+                ::std::result::Result::Ok(value)
             }
         }
 
-        impl From<Binary> for #name {
-            fn from(value: Binary) -> Self {
-                Self::Binary(value)
+        impl #impl_generics ::std::convert::TryFrom<::messagepack_rs::extension::Extension> for #name #ty_generics #where_clause {
+            type Error = ::messagepack_rs::deserializable::DeserializeError;
+
+            fn try_from(extension: ::messagepack_rs::extension::Extension) -> ::std::result::Result<Self, Self::Error> {
+                ::std::convert::TryFrom::try_from(&extension)
             }
         }
+    };
+    Ok(gen.into())
+}
 
-        impl From<&str> for #name {
-            fn from(value: &str) -> Self {
-                Self::String(String::from(value))
-            }
+#[proc_macro_derive(MessagePackValue, attributes(msgpack, msgpack_ext))]
+pub fn message_pack_value_macro_derive(input: TokenStream) -> TokenStream {
+    let ast = syn::parse(input).unwrap();
+    impl_message_pack_value_macro(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+fn impl_message_pack_value_macro(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let mut serialize_arms = Vec::new();
+    let mut from_value_arms = Vec::new();
+    let mut to_value_arms = Vec::new();
+    let mut extension_ident = None;
+    let mut extras = Vec::new();
+    for (variant, role) in variant_roles(ast)? {
+        let ident = &variant.ident;
+        let ExtensionAttributes { t, layout } = parse_extension_attributes(&variant.attrs)?;
+        if let Some(lit) = layout {
+            return Err(syn::Error::new(lit.span(), "layout belongs on the extension type, not on the variant"));
+        }
+        match (role, t) {
+            (Some("nil"), None) => {
+                serialize_arms.push(quote! { Self::#ident => Self::serialize_nil(), });
+                from_value_arms.push(quote! { ::messagepack_rs::value::Value::Nil => Self::#ident, });
+                to_value_arms.push(quote! { #name::#ident => ::messagepack_rs::value::Value::Nil, });
+            },
+            (Some(role), None) => {
+                let serialize = syn::Ident::new(&format!("serialize_{}", role), ident.span());
+                serialize_arms.push(quote! { Self::#ident(v) => Self::#serialize(v), });
+                let value_variant = syn::Ident::new(ROLES.iter().find(|(r, _)| *r == role).expect("known role").1, ident.span());
+                match role {
+                    "array" => {
+                        from_value_arms.push(quote! {
+                            ::messagepack_rs::value::Value::Array(v) => Self::#ident(v.into_iter().map(::std::convert::From::from).collect()),
+                        });
+                        to_value_arms.push(quote! {
+                            #name::#ident(v) => ::messagepack_rs::value::Value::Array(v.into_iter().map(::std::convert::TryFrom::try_from).collect::<::std::result::Result<_, _>>()?),
+                        });
+                    },
+                    "map" => {
+                        from_value_arms.push(quote! {
+                            ::messagepack_rs::value::Value::Map(v) => Self::#ident(v.into_iter().map(|(k, v)| (::std::convert::From::from(k), ::std::convert::From::from(v))).collect()),
+                        });
+                        to_value_arms.push(quote! {
+                            #name::#ident(v) => ::messagepack_rs::value::Value::Map(v.into_iter().map(|(k, v)| ::std::result::Result::Ok((::std::convert::From::from(k), ::std::convert::TryFrom::try_from(v)?))).collect::<::std::result::Result<_, ::messagepack_rs::serializable::SerializeError>>()?),
+                        });
+                    },
+                    _ => {
+                        if role == "extension" {
+                            extension_ident = Some(ident);
+                        } else {
+                            from_value_arms.push(quote! {
+                                ::messagepack_rs::value::Value::#value_variant(v) => Self::#ident(::std::convert::From::from(v)),
+                            });
+                        }
+                        to_value_arms.push(quote! {
+                            #name::#ident(v) => ::messagepack_rs::value::Value::#value_variant(::std::convert::From::from(v)),
+                        });
+                    },
+                }
+            },
+            (None, Some(t)) => {
+                extras.push((ident, t));
+                to_value_arms.push(quote! {
+                    #name::#ident(v) => ::messagepack_rs::value::Value::Extension(<::messagepack_rs::extension::Extension as ::std::convert::TryFrom<&_>>::try_from(&v)?),
+                });
+                serialize_arms.push(quote! {
+                    Self::#ident(v) => Self::serialize_extension(<::messagepack_rs::extension::Extension as ::std::convert::TryFrom<&_>>::try_from(&v)?),
+                });
+            },
+            (Some(_), Some(_)) => return Err(syn::Error::new_spanned(variant, "a variant with a role cannot also be an extension")),
+            (None, None) => return Err(syn::Error::new_spanned(variant, "variant needs #[msgpack_ext(type = ...)] to be encoded as an extension")),
         }
+    }
 
-        impl From<Vec<Self>> for #name {
-            fn from(value: Vec<Self>) -> Self {
-                Self::Array(value)
-            }
+    let extension_ident = extension_ident.expect("checked by variant_roles");
+    let extension_arms = extras.iter().map(|(ident, t)| quote! {
+        #t => match ::std::convert::TryFrom::try_from(&v) {
+            ::std::result::Result::Ok(v) => Self::#ident(v),
+            ::std::result::Result::Err(_) => Self::#extension_ident(::std::convert::From::from(v)),
+        },
+    });
+
+    // The payload is read up to its end rather than into a buffer of the size the header claims, which may be forged.
+    let extra_types = extras.iter().map(|(_, t)| t).collect::<Vec<_>>();
+    let deserialize_arm = if extra_types.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            #(#extra_types)|* => {
+                let mut data = ::std::vec::Vec::new();
+                ::std::io::Read::read_to_end(&mut ::std::io::Read::take(buf_reader, size as u64), &mut data).or(::std::result::Result::Err(::messagepack_rs::deserializable::DeserializeError::InvalidValue))?;
+                if data.len() != size {
+                    return ::std::result::Result::Err(::messagepack_rs::deserializable::DeserializeError::InvalidValue);
+                }
+                ::std::result::Result::Ok(::std::convert::From::from(::messagepack_rs::value::Value::Extension(::messagepack_rs::extension::Extension { t, data })))
+            },
         }
+    };
 
-        impl From<&[Self]> for #name {
-            fn from(value: &[Self]) -> Self {
-                Self::Array(Vec::from(value))
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let gen = quote! {
+        /// Extensions whose type belongs to an extra variant become that variant when they decode, and stay extensions otherwise.
+        /// `deserialize` follows the same rule, so a malformed payload of an extra variant's type never fails the whole message.
+        /// A str that is not valid UTF-8 becomes a string with its invalid sequences replaced by U+FFFD.
+        impl #impl_generics ::std::convert::From<::messagepack_rs::value::Value> for #name #ty_generics #where_clause {
+            fn from(value: ::messagepack_rs::value::Value) -> Self {
+                match value {
+                    #(#from_value_arms)*
+                    ::messagepack_rs::value::Value::Extension(v) => match v.t {
+                        #(#extension_arms)*
+                        _ => Self::#extension_ident(::std::convert::From::from(v)),
+                    },
+                    ::messagepack_rs::value::Value::TypedExtension(v) => ::std::convert::From::from(::messagepack_rs::value::Value::Extension(v.to_extension())),
+                    ::messagepack_rs::value::Value::FormattedTimestamp(v, _) => ::std::convert::From::from(::messagepack_rs::value::Value::Timestamp(v)),
+                    ::messagepack_rs::value::Value::NonUtf8String(v) => ::std::convert::From::from(::messagepack_rs::value::Value::String(::std::string::String::from_utf8_lossy(&v).into_owned())),
+                }
             }
         }
 
-        impl From<BTreeMap<String, Self>> for #name {
-            fn from(value: BTreeMap<String, Self>) -> Self {
-                Self::Map(value)
+        /// Extra variants are encoded to extensions, which is the only way the conversion can fail.
+        impl #impl_generics ::std::convert::TryFrom<#name #ty_generics> for ::messagepack_rs::value::Value #where_clause {
+            type Error = ::messagepack_rs::serializable::SerializeError;
+
+            fn try_from(value: #name #ty_generics) -> ::std::result::Result<Self, Self::Error> {
+                ::std::result::Result::Ok(match value {
+                    #(#to_value_arms)*
+                })
             }
         }
 
-        impl From<Extension> for #name {
-            fn from(value: Extension) -> Self {
-                Self::Extension(value)
+        impl #impl_generics ::messagepack_rs::serializable::Serializable for #name #ty_generics #where_clause {
+            fn serialize(self) -> ::std::result::Result<::std::vec::Vec<u8>, ::messagepack_rs::serializable::SerializeError> {
+                match self {
+                    #(#serialize_arms)*
+                }
             }
         }
 
-        impl From<DateTime<Utc>> for #name {
-            fn from(value: DateTime<Utc>) -> Self {
-                Self::Timestamp(value)
+        impl #impl_generics ::messagepack_rs::deserializable::Deserializable for #name #ty_generics #where_clause {
+            fn deserialize_extension_for_the_you_type_defined<R: ::std::io::Read>(t: i8, size: usize, buf_reader: &mut R) -> ::std::result::Result<Self, ::messagepack_rs::deserializable::DeserializeError> {
+                match t {
+                    #deserialize_arm
+                    _ => Self::deserialize_extension_others(t, size, buf_reader),
+                }
             }
         }
     };
-    gen.into()
+    Ok(gen.into())
 }
@@ -1,14 +1,32 @@
-use chrono::prelude::*;
+use messagepack_rs::binary::Binary;
 use messagepack_rs::deserializable::Deserializable;
 use messagepack_rs::serializable::Serializable;
-use messagepack_rs::value::Value;
+use messagepack_rs::value::{FloatEq, HashableValue, Value};
+use std::collections::{HashMap, HashSet};
 use std::io::{BufReader, Cursor};
+use std::time::SystemTime;
 
 fn main() {
-    let value = Value::from(vec![Value::from(Utc::now()), Value::from(123u8), Value::from("test"), Value::from(true), Value::Nil, Value::from(None::<u8>)]);
+    let value = Value::from(vec![Value::from(SystemTime::now()), Value::from(123u8), Value::from("test"), Value::from(true), Value::Nil, Value::from(None::<u8>)]);
     println!("{:?}", value);
     let serialized_value = value.serialize().unwrap();
     println!("{:?}", serialized_value);
     let deserialized_value = Value::deserialize(&mut BufReader::new(Cursor::new(serialized_value))).unwrap();
     println!("{:?}", deserialized_value);
+
+    let mut map = HashMap::new();
+    map.insert("key", 1usize);
+    let value: Value = vec![Value::from((1u8, "two", 'c')), Value::from(map), Value::from(Binary(vec![0u8, 1])), (0..3).map(Value::from).collect()].into_iter().collect();
+    println!("{:?}", value);
+
+    let a = Value::from(vec![Value::UInt32(1), Value::Int64(-1), Value::Float32(0.1)]);
+    let b = Value::from(vec![Value::Int8(1), Value::Int8(-1), Value::Float64(0.1)]);
+    println!("{:?}", a.clone().normalize());
+    println!("{} {}", a.semantic_eq(&b, FloatEq::Bits), a.semantic_eq(&b, FloatEq::Tolerance(1e-6)));
+
+    let mut values = vec![Value::from("b"), Value::Float64(f64::NAN), Value::Nil, Value::Float64(-1.0), Value::from("a")];
+    values.sort_by(Value::total_cmp);
+    println!("{:?}", values);
+    let unique: HashSet<HashableValue> = vec![Value::UInt8(1), Value::Int64(1).normalize(), Value::from("a")].into_iter().map(HashableValue::from).collect();
+    println!("{}", unique.len());
 }
@@ -0,0 +1,19 @@
+use messagepack_rs::diff;
+use messagepack_rs::value::Value;
+
+fn main() {
+    let expected: Value = vec![
+        (String::from("id"), Value::from(1u8)),
+        (String::from("tags"), Value::from(["a", "b", "c"])),
+        (String::from("a/b"), Value::from(true)),
+    ].into_iter().collect();
+    let actual: Value = vec![
+        (String::from("id"), Value::from("1")),
+        (String::from("tags"), Value::from(["a", "x", "c", "d"])),
+        (String::from("extra"), Value::Nil),
+    ].into_iter().collect();
+
+    let changes = diff::diff(&expected, &actual);
+    println!("{:?}", changes.iter().map(|change| change.path()).collect::<Vec<_>>());
+    print!("{}", diff::render(&changes));
+}
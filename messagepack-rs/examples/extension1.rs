@@ -2,9 +2,12 @@ use messagepack_rs::deserializable::Deserializable;
 use messagepack_rs::extension::Extension;
 use messagepack_rs::serializable::Serializable;
 use messagepack_rs::value::Value;
+use messagepack_rs_macros::MessagePackExtension;
+use std::convert::TryFrom;
 use std::io::{BufReader, Cursor};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, MessagePackExtension)]
+#[msgpack_ext(type = 0)]
 struct Rgba {
     r: u8,
     g: u8,
@@ -12,15 +15,13 @@ struct Rgba {
     a: u8,
 }
 
-impl From<Rgba> for Value {
-    fn from(value: Rgba) -> Self {
-        Self::Extension(Extension { t: 0, data: vec![value.r, value.g, value.b, value.a] })
-    }
-}
+#[derive(Clone, Debug, PartialEq, MessagePackExtension)]
+#[msgpack_ext(type = 1, layout = "msgpack")]
+struct Label(String, u32);
 
 fn main() {
     let rgba = Rgba { r: 5, g: 10, b: 15, a: 20 };
-    let value = Value::from(rgba);
+    let value = Value::from(vec![Value::from(Extension::try_from(&rgba).unwrap()), Value::from(Extension::try_from(Label(String::from("test"), 1)).unwrap())]);
     println!("{:?}", value);
 
     let serialized_value = value.serialize().unwrap();
@@ -28,4 +29,11 @@ fn main() {
 
     let deserialized_value = Value::deserialize(&mut BufReader::new(Cursor::new(serialized_value))).unwrap();
     println!("{:?}", deserialized_value);
+
+    if let Value::Array(v) = deserialized_value {
+        if let (Value::Extension(rgba), Value::Extension(label)) = (&v[0], &v[1]) {
+            println!("{:?}", Rgba::try_from(rgba));
+            println!("{:?}", Label::try_from(label));
+        }
+    }
 }
@@ -0,0 +1,44 @@
+use messagepack_rs::deserializable::DeserializeError;
+use messagepack_rs::extension::registry::ExtensionRegistry;
+use messagepack_rs::serializable::Serializable;
+use messagepack_rs::value::Value;
+use std::io::{BufReader, Cursor};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Rgba {
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+}
+
+fn encode_rgba(v: &Rgba) -> Vec<u8> {
+    vec![v.r, v.g, v.b, v.a]
+}
+
+fn decode_rgba(data: &[u8]) -> Result<Rgba, DeserializeError> {
+    match data {
+        [r, g, b, a] => Ok(Rgba { r: *r, g: *g, b: *b, a: *a }),
+        _ => Err(DeserializeError::InvalidLength),
+    }
+}
+
+fn main() {
+    let mut registry = ExtensionRegistry::new();
+    registry.register(0, encode_rgba, decode_rgba);
+
+    let rgba = Rgba { r: 5, g: 10, b: 15, a: 20 };
+    let value = Value::from(vec![Value::from(registry.wrap(rgba).unwrap()), Value::from(1u8)]);
+    println!("{:?}", value);
+
+    let serialized_value = value.serialize().unwrap();
+    println!("{:?}", serialized_value);
+
+    let deserialized_value = registry.deserialize(&mut BufReader::new(Cursor::new(serialized_value))).unwrap();
+    println!("{:?}", deserialized_value);
+    if let Value::Array(v) = deserialized_value {
+        if let Value::TypedExtension(v) = &v[0] {
+            println!("{:?}", v.downcast_ref::<Rgba>());
+        }
+    }
+}
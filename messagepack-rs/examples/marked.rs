@@ -0,0 +1,14 @@
+use messagepack_rs::marked::{Marked, Node};
+use messagepack_rs::serializable::Serializable;
+
+fn main() {
+    // {"id": 7, "name": "a"} written with a Map16 header and a UInt32 integer.
+    let original = [0xde, 0x00, 0x02, 0xa2, b'i', b'd', 0xce, 0x00, 0x00, 0x00, 0x07, 0xa4, b'n', b'a', b'm', b'e', 0xa1, b'a'];
+    let mut marked = Marked::deserialize(&mut &original[..]).unwrap();
+    println!("{:?}", marked.pointer("/id").unwrap());
+
+    marked.pointer_mut("/name").unwrap().node = Node::String(String::from("b"));
+    marked.pointer_mut("/id").unwrap().node = Node::UInt(8);
+    println!("{:02x?}", original);
+    println!("{:02x?}", marked.serialize().unwrap());
+}
@@ -0,0 +1,23 @@
+use messagepack_rs::deserializable::Deserializable;
+use messagepack_rs::pointer;
+use messagepack_rs::serializable::Serializable;
+use messagepack_rs::value::Value;
+use std::collections::BTreeMap;
+
+fn main() {
+    let mut headers = BTreeMap::new();
+    headers.insert(String::from("trace_id"), Value::from("abc123"));
+    let mut message = BTreeMap::new();
+    message.insert(String::from("headers"), Value::from(headers));
+    message.insert(String::from("body"), Value::from(vec![Value::from(1u8), Value::from(2u8)]));
+    let serialized_value = Value::from(message).serialize().unwrap();
+
+    println!("{:?}", pointer::lookup(&serialized_value, "/headers/trace_id").unwrap());
+    println!("{:?}", pointer::lookup_range(&serialized_value, "/body/1").unwrap());
+    println!("{:?}", pointer::lookup(&serialized_value, "/missing").unwrap());
+
+    let patched_value = pointer::set(&serialized_value, "/headers/trace_id", Value::from("def456")).unwrap();
+    let patched_value = pointer::set(&patched_value, "/body/-", Value::from(3u8)).unwrap();
+    let patched_value = pointer::remove(&patched_value, "/body/0").unwrap();
+    println!("{:?}", Value::deserialize(&mut &patched_value[..]).unwrap());
+}
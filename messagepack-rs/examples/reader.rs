@@ -0,0 +1,33 @@
+use messagepack_rs::decodable::Decodable;
+use messagepack_rs::reader::{self, Reader, Tokenizer};
+use messagepack_rs::serializable::Serializable;
+use messagepack_rs::value::Value;
+use std::collections::BTreeMap;
+
+fn main() {
+    let mut map = BTreeMap::new();
+    map.insert(String::from("id"), Value::from(1234u16));
+    map.insert(String::from("name"), Value::from("test"));
+    let serialized_value = Value::from(vec![Value::from(map), Value::from(-1i8)]).serialize().unwrap();
+
+    for token in Tokenizer::new(&serialized_value) {
+        println!("{:?}", token.unwrap());
+    }
+
+    println!("skipped {} bytes", reader::skip_value(&serialized_value[1..]).unwrap());
+
+    let mut reader = Reader::new(&serialized_value[..]);
+    let mut buf = Vec::new();
+    println!("array len: {}", reader.read_array_len().unwrap());
+    println!("map len: {}", reader.read_map_len().unwrap());
+    println!("key: {}", reader.read_str(&mut buf).unwrap());
+    println!("id: {}", reader.read_u32().unwrap());
+    println!("key: {}", reader.read_str(&mut buf).unwrap());
+    println!("name: {}", reader.read_str(&mut buf).unwrap());
+    println!("u8: {:?}", reader.read_u8());
+
+    let serialized_value = (vec![1u32, 2, 3], Some("test"), None::<bool>).serialize().unwrap();
+    let mut reader = Reader::new(&serialized_value[..]);
+    println!("{:?}", <(Vec<u32>, Option<String>, Option<bool>)>::decode(&mut reader));
+    println!("{:?}", Vec::<u16>::decode(&mut Reader::new(&[0x91, 0xce, 0x00, 0x01, 0x00, 0x00][..])));
+}
@@ -0,0 +1,17 @@
+use messagepack_rs::repack;
+
+fn main() {
+    // [{"id": 7}, timestamp 1] written with Array16, Map16, Str8, UInt32 and a 96-bit timestamp.
+    let wasteful = [
+        0xdc, 0x00, 0x02,
+        0xde, 0x00, 0x01, 0xd9, 0x02, b'i', b'd', 0xce, 0x00, 0x00, 0x00, 0x07,
+        0xc7, 0x0c, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    ];
+    let (minimal, repacked) = repack::repack_slice(&wasteful).unwrap();
+    println!("{:02x?}", minimal);
+    println!("{:?}, saved {} bytes", repacked, repacked.bytes_saved());
+
+    let mut w = Vec::new();
+    let repacked = repack::repack(&[0xd1, 0x00, 0x05, 0xd1, 0xff, 0xfe][..], &mut w).unwrap();
+    println!("{:02x?} {:?}", w, repacked);
+}
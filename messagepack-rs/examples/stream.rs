@@ -1,5 +1,8 @@
+use messagepack_rs::binary::Binary;
+use messagepack_rs::reader::{Options, Reader};
 use messagepack_rs::stream::serializer::Serializer;
 use messagepack_rs::value::Value;
+use messagepack_rs::visitor::Visitor;
 use std::io::{BufReader, Cursor};
 
 fn main() {
@@ -10,10 +13,25 @@ fn main() {
     stream_serializer.serialize(Value::from(false)).unwrap();
     stream_serializer.serialize(Value::from("test")).unwrap();
     stream_serializer.serialize(Value::Nil).unwrap();
+    stream_serializer.serialize(vec![1u32, 2, 3]).unwrap();
+    stream_serializer.serialize(("id", 7u8, Some(0.5f64))).unwrap();
     stream_serializer.flush().unwrap();
     println!("{:?}", stream_serializer.get_ref());
 
     let buf_reader = BufReader::new(Cursor::new(stream_serializer.get_ref()));
     let stream_deserializer = messagepack_rs::stream::deserializer::Deserializer::<Value, _>::new(buf_reader);
     stream_deserializer.for_each(|v| println!("{:?}", v));
+
+    let buf_reader = BufReader::new(Cursor::new(stream_serializer.get_ref()));
+    let raw_deserializer = messagepack_rs::stream::raw_deserializer::RawDeserializer::new(buf_reader);
+    raw_deserializer.for_each(|v| println!("{:?}", v));
+
+    let mut legacy_serializer = Serializer::new(Vec::new());
+    legacy_serializer.serialize_legacy(("test", Binary(vec![0xff, 0x00]))).unwrap();
+    println!("{:?}", legacy_serializer.serialize_legacy(Value::from(std::time::SystemTime::now())));
+    legacy_serializer.flush().unwrap();
+    println!("{:?}", legacy_serializer.get_ref());
+
+    let options = Options { str_as_binary: true, ..Options::default() };
+    println!("{:?}", Value::visit(&mut Reader::with_options(&legacy_serializer.get_ref()[..], options)));
 }
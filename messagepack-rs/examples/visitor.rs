@@ -0,0 +1,66 @@
+use messagepack_rs::deserializable::DeserializeError;
+use messagepack_rs::reader::{InvalidUtf8, Options, Reader};
+use messagepack_rs::serializable::Serializable;
+use messagepack_rs::value::Value;
+use messagepack_rs::visitor::Visitor;
+use std::io::Read;
+
+#[derive(Debug)]
+enum Command {
+    Ping,
+    Echo(String),
+}
+
+impl Visitor for Command {
+    fn visit_str(v: String) -> Result<Self, DeserializeError> {
+        match v.as_str() {
+            "ping" => Ok(Command::Ping),
+            _ => Err(DeserializeError::InvalidValue),
+        }
+    }
+
+    fn visit_array<R: Read>(len: usize, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        if len != 2 {
+            return Err(DeserializeError::InvalidLength);
+        }
+        match reader.decode::<String>()?.as_str() {
+            "echo" => Ok(Command::Echo(reader.decode()?)),
+            _ => Err(DeserializeError::InvalidValue),
+        }
+    }
+}
+
+fn main() {
+    let serialized_value = "ping".serialize().unwrap();
+    println!("{:?}", Command::visit(&mut Reader::new(&serialized_value[..])));
+
+    let serialized_value = ("echo", "hello").serialize().unwrap();
+    if let Ok(Command::Echo(message)) = Command::visit(&mut Reader::new(&serialized_value[..])) {
+        println!("echo: {}", message);
+    }
+    println!("{:?}", Value::visit(&mut Reader::new(&serialized_value[..])));
+
+    let serialized_value = 1u8.serialize().unwrap();
+    println!("{:?}", Command::visit(&mut Reader::new(&serialized_value[..])));
+
+    let malformed_timestamp = [0xd7, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00];
+    println!("{:?}", Value::visit(&mut Reader::new(&malformed_timestamp[..])));
+    let options = Options { lenient_timestamps: true, ..Options::default() };
+    println!("{:?}", Value::visit(&mut Reader::with_options(&malformed_timestamp[..], options)));
+
+    let timestamp96 = [0xc7, 0x0c, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+    let options = Options { preserve_timestamp_format: true, ..Options::default() };
+    let value = Value::visit(&mut Reader::with_options(&timestamp96[..], options)).unwrap();
+    println!("{:?}", value);
+    println!("{:?}", value.serialize().unwrap() == timestamp96);
+
+    let latin1 = [0x81, 0xa1, 0x6b, 0xa4, 0x63, 0x61, 0x66, 0xe9];
+    let latin1_key = [0x81, 0xa2, 0xe9, 0x74, 0x01];
+    println!("{:?}", Value::visit(&mut Reader::new(&latin1[..])));
+    for invalid_utf8 in [InvalidUtf8::Keep, InvalidUtf8::Lossy, InvalidUtf8::Binary] {
+        let options = Options { invalid_utf8, ..Options::default() };
+        let value = Value::visit(&mut Reader::with_options(&latin1[..], options)).unwrap();
+        println!("{:?} {:?}", value, value.clone().serialize().unwrap());
+        println!("{:?}", Value::visit(&mut Reader::with_options(&latin1_key[..], options)));
+    }
+}
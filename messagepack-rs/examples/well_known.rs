@@ -0,0 +1,33 @@
+use messagepack_rs::extension::well_known::{BigInt, Decimal, OffsetTimestamp, Uuid, WellKnownTypes};
+use messagepack_rs::serializable::Serializable;
+use messagepack_rs::timestamp::Timestamp;
+use messagepack_rs::value::Value;
+use std::io::{BufReader, Cursor};
+use std::net::IpAddr;
+use std::time::Duration;
+
+fn main() {
+    let types = WellKnownTypes::default();
+    let value = Value::from(vec![
+        types.to_value("67e55044-10b1-426f-9247-bb680e5fe0c8".parse::<Uuid>().unwrap()),
+        types.to_value(BigInt::from(i128::MIN)),
+        types.to_value("-123.0045".parse::<Decimal>().unwrap()),
+        types.to_value(Duration::from_millis(1500)),
+        types.to_value("::1".parse::<IpAddr>().unwrap()),
+        types.to_value(OffsetTimestamp { timestamp: Timestamp::new(1_700_000_000, 0), offset_seconds: 9 * 60 * 60 }),
+    ]);
+    println!("{:?}", value);
+
+    let serialized_value = value.serialize().unwrap();
+    println!("{:?}", serialized_value);
+
+    let deserialized_value = types.registry().deserialize(&mut BufReader::new(Cursor::new(serialized_value))).unwrap();
+    println!("{:?}", deserialized_value);
+    if let Value::Array(v) = deserialized_value {
+        if let (Value::TypedExtension(uuid), Value::TypedExtension(big_int), Value::TypedExtension(decimal)) = (&v[0], &v[1], &v[2]) {
+            println!("{}", uuid.downcast_ref::<Uuid>().unwrap());
+            println!("{}", big_int.downcast_ref::<BigInt>().unwrap());
+            println!("{}", decimal.downcast_ref::<Decimal>().unwrap());
+        }
+    }
+}
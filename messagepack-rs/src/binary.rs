@@ -1,2 +1,8 @@
 #[derive(Clone, Debug, PartialEq)]
 pub struct Binary(pub Vec<u8>);
+
+impl From<Vec<u8>> for Binary {
+    fn from(value: Vec<u8>) -> Self {
+        Binary(value)
+    }
+}
@@ -0,0 +1,294 @@
+use crate::binary::Binary;
+use crate::deserializable::DeserializeError;
+use crate::extension::Extension;
+use crate::reader::{Header, Reader, MAX_PREALLOCATED};
+use crate::timestamp::Timestamp;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::hash::{BuildHasher, Hash};
+use std::io::Read;
+use std::time::SystemTime;
+
+/// A type decoded straight from a `Reader`, checking each marker against the type it expects.
+///
+/// A value of the wrong type fails with `InvalidMarker` and an integer that does not fit fails with `OutOfRange`.
+pub trait Decodable: Sized {
+    fn decode<R: Read>(reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        let header = reader.read_header()?;
+        Self::decode_with_header(header, reader)
+    }
+
+    /// Decodes the value whose header has already been read.
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError>;
+}
+
+impl Decodable for bool {
+    fn decode_with_header<R: Read>(header: Header, _: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Bool(v) => Ok(v),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+}
+
+/// Accepts any integer marker and fails with `OutOfRange` when the value does not fit in `T`.
+fn integer<T: TryFrom<u64> + TryFrom<i64>>(header: Header) -> Result<T, DeserializeError> {
+    match header {
+        Header::UInt(v) => T::try_from(v).or(Err(DeserializeError::OutOfRange)),
+        Header::Int(v) => T::try_from(v).or(Err(DeserializeError::OutOfRange)),
+        _ => Err(DeserializeError::InvalidMarker),
+    }
+}
+
+macro_rules! impl_decodable_for_integer {
+    ($($t:ty),+) => {
+        $(
+            impl Decodable for $t {
+                fn decode_with_header<R: Read>(header: Header, _: &mut Reader<R>) -> Result<Self, DeserializeError> {
+                    integer(header)
+                }
+            }
+        )+
+    };
+}
+
+impl_decodable_for_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl Decodable for f32 {
+    fn decode_with_header<R: Read>(header: Header, _: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Float32(v) => Ok(v),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+}
+
+/// Both float widths are accepted since widening is lossless.
+impl Decodable for f64 {
+    fn decode_with_header<R: Read>(header: Header, _: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Float32(v) => Ok(f64::from(v)),
+            Header::Float64(v) => Ok(v),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+}
+
+impl Decodable for String {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Str(size) => {
+                let mut buf = Vec::new();
+                reader.read_payload(size, &mut buf)?;
+                String::from_utf8(buf).or(Err(DeserializeError::InvalidValue))
+            },
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+}
+
+impl Decodable for Binary {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Bin(size) => {
+                let mut buf = Vec::new();
+                reader.read_payload(size, &mut buf)?;
+                Ok(Binary(buf))
+            },
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+}
+
+/// Any extension, including a timestamp, is returned undecoded.
+impl Decodable for Extension {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Ext(t, size) => {
+                let mut data = Vec::new();
+                reader.read_payload(size, &mut data)?;
+                Ok(Extension { t, data })
+            },
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+}
+
+impl Decodable for Timestamp {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Ext(-1, size) => {
+                let mut data = Vec::new();
+                reader.read_payload(size, &mut data)?;
+                Timestamp::from_extension_data(&data)
+            },
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+}
+
+impl Decodable for SystemTime {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        SystemTime::try_from(Timestamp::decode_with_header(header, reader)?)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Decodable for chrono::DateTime<chrono::Utc> {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        Self::try_from(Timestamp::decode_with_header(header, reader)?)
+    }
+}
+
+#[cfg(feature = "time")]
+impl Decodable for time::OffsetDateTime {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        Self::try_from(Timestamp::decode_with_header(header, reader)?)
+    }
+}
+
+/// Nil decodes to `None`; anything else must decode as `T`.
+impl<T: Decodable> Decodable for Option<T> {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Nil => Ok(None),
+            header => T::decode_with_header(header, reader).map(Some),
+        }
+    }
+}
+
+impl<T: Decodable> Decodable for Vec<T> {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Array(size) => {
+                let mut buf = Vec::with_capacity(size.min(MAX_PREALLOCATED));
+                for _ in 0..size {
+                    buf.push(T::decode(reader)?);
+                }
+                Ok(buf)
+            },
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+}
+
+impl<K: Decodable + Ord, V: Decodable> Decodable for BTreeMap<K, V> {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Map(size) => {
+                let mut buf = BTreeMap::new();
+                for _ in 0..size {
+                    buf.insert(K::decode(reader)?, V::decode(reader)?);
+                }
+                Ok(buf)
+            },
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+}
+
+impl<K: Decodable + Eq + Hash, V: Decodable, S: BuildHasher + Default> Decodable for HashMap<K, V, S> {
+    fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Map(size) => {
+                let mut buf = HashMap::with_capacity_and_hasher(size.min(MAX_PREALLOCATED), S::default());
+                for _ in 0..size {
+                    buf.insert(K::decode(reader)?, V::decode(reader)?);
+                }
+                Ok(buf)
+            },
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+}
+
+macro_rules! impl_decodable_for_tuple {
+    ($len:expr, $($t:ident),+) => {
+        /// Tuples are read from arrays of exactly their length.
+        impl<$($t: Decodable),+> Decodable for ($($t,)+) {
+            fn decode_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+                match header {
+                    Header::Array($len) => Ok(($($t::decode(reader)?,)+)),
+                    Header::Array(_) => Err(DeserializeError::InvalidLength),
+                    _ => Err(DeserializeError::InvalidMarker),
+                }
+            }
+        }
+    };
+}
+
+impl_decodable_for_tuple!(1, A);
+impl_decodable_for_tuple!(2, A, B);
+impl_decodable_for_tuple!(3, A, B, C);
+impl_decodable_for_tuple!(4, A, B, C, D);
+impl_decodable_for_tuple!(5, A, B, C, D, E);
+impl_decodable_for_tuple!(6, A, B, C, D, E, F);
+impl_decodable_for_tuple!(7, A, B, C, D, E, F, G);
+impl_decodable_for_tuple!(8, A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode<T: Decodable>(buf: &[u8]) -> Result<T, DeserializeError> {
+        T::decode(&mut Reader::new(buf))
+    }
+
+    #[test]
+    fn forged_container_lengths_fail() {
+        assert!(decode::<Vec<u64>>(&[0xdd, 0xff, 0xff, 0xff, 0xff, 0x01]).is_err());
+        assert!(decode::<HashMap<u64, u64>>(&[0xdf, 0xff, 0xff, 0xff, 0xff, 0x01, 0x02]).is_err());
+    }
+
+    #[test]
+    fn integers_at_the_edges_of_their_range() {
+        assert_eq!(decode::<u8>(&[0xcc, 0xff]).unwrap(), u8::MAX);
+        assert!(matches!(decode::<u8>(&[0xcd, 0x01, 0x00]), Err(DeserializeError::OutOfRange)));
+        assert!(matches!(decode::<u8>(&[0xff]), Err(DeserializeError::OutOfRange)));
+        assert_eq!(decode::<i8>(&[0x7f]).unwrap(), i8::MAX);
+        assert_eq!(decode::<i8>(&[0xd0, 0x80]).unwrap(), i8::MIN);
+        assert!(matches!(decode::<i8>(&[0xcc, 0x80]), Err(DeserializeError::OutOfRange)));
+        assert!(matches!(decode::<i8>(&[0xd1, 0xff, 0x7f]), Err(DeserializeError::OutOfRange)));
+        assert_eq!(decode::<i64>(&[0xcf, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap(), i64::MAX);
+        assert!(matches!(decode::<i64>(&[0xcf, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]), Err(DeserializeError::OutOfRange)));
+        assert!(matches!(decode::<u64>(&[0xd3, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]), Err(DeserializeError::OutOfRange)));
+    }
+
+    #[test]
+    fn integers_widen_and_narrow_across_markers() {
+        assert_eq!(decode::<u64>(&[0x05]).unwrap(), 5);
+        assert_eq!(decode::<u16>(&[0xd1, 0x00, 0x05]).unwrap(), 5);
+        assert_eq!(decode::<u8>(&[0xcf, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05]).unwrap(), 5);
+        assert_eq!(decode::<i64>(&[0xfb]).unwrap(), -5);
+        assert_eq!(decode::<i16>(&[0xd3, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfb]).unwrap(), -5);
+        assert_eq!(decode::<i32>(&[0xce, 0x7f, 0xff, 0xff, 0xff]).unwrap(), i32::MAX);
+        assert_eq!(decode::<usize>(&[0xcd, 0x01, 0x00]).unwrap(), 256);
+        assert_eq!(decode::<isize>(&[0xd0, 0x80]).unwrap(), -128);
+    }
+
+    #[test]
+    fn wrong_markers_fail() {
+        assert!(matches!(decode::<u8>(&[0xa1, b'a']), Err(DeserializeError::InvalidMarker)));
+        assert!(matches!(decode::<u8>(&[0xca, 0x00, 0x00, 0x00, 0x00]), Err(DeserializeError::InvalidMarker)));
+        assert!(matches!(decode::<bool>(&[0xc0]), Err(DeserializeError::InvalidMarker)));
+        assert!(matches!(decode::<f32>(&[0xcb, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]), Err(DeserializeError::InvalidMarker)));
+        assert!(matches!(decode::<String>(&[0xc4, 0x01, b'a']), Err(DeserializeError::InvalidMarker)));
+    }
+
+    #[test]
+    fn floats_widen() {
+        assert_eq!(decode::<f64>(&[0xca, 0x3f, 0xc0, 0x00, 0x00]).unwrap(), 1.5);
+        assert_eq!(decode::<f32>(&[0xca, 0x3f, 0xc0, 0x00, 0x00]).unwrap(), 1.5);
+    }
+
+    #[test]
+    fn containers_and_options() {
+        assert_eq!(decode::<Option<u8>>(&[0xc0]).unwrap(), None);
+        assert_eq!(decode::<Option<u8>>(&[0x01]).unwrap(), Some(1));
+        assert_eq!(decode::<Vec<u16>>(&[0x92, 0x01, 0xcd, 0x01, 0x00]).unwrap(), [1, 256]);
+        assert!(matches!(decode::<Vec<u8>>(&[0x92, 0x01, 0xcd, 0x01, 0x00]), Err(DeserializeError::OutOfRange)));
+        assert_eq!(decode::<BTreeMap<String, i8>>(&[0x81, 0xa1, b'a', 0xff]).unwrap(), vec![(String::from("a"), -1)].into_iter().collect());
+        assert_eq!(decode::<(u8, String)>(&[0x92, 0x01, 0xa1, b'a']).unwrap(), (1, String::from("a")));
+        assert!(matches!(decode::<(u8, String)>(&[0x91, 0x01]), Err(DeserializeError::InvalidLength)));
+    }
+}
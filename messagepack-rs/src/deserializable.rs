@@ -1,8 +1,8 @@
 use byteorder::{BigEndian, ReadBytesExt};
-use chrono::prelude::*;
 use crate::binary::Binary;
 use crate::extension::Extension;
 use crate::marker::Marker;
+use crate::timestamp::Timestamp;
 use std::collections::BTreeMap;
 use std::io::Read;
 
@@ -11,9 +11,17 @@ pub enum DeserializeError {
     InvalidLength,
     InvalidMarker,
     InvalidValue,
+    OutOfRange,
+    /// The data of a timestamp extension is not 4, 8 or 12 bytes long.
+    InvalidTimestampLength,
+    /// The nanoseconds of a timestamp extension exceed 999,999,999.
+    InvalidTimestampNanoseconds,
 }
 
-pub trait Deserializable: Sized + From<Option<Self>> + From<bool> + From<Binary> + From<f32> + From<f64> + From<u8> + From<u16> + From<u32> + From<u64> + From<i8> + From<i16> + From<i32> + From<i64> + From<String> + From<Vec<Self>> + From<BTreeMap<String, Self>> + From<Extension> + From<DateTime<Utc>> {
+/// A type built from MessagePack through a conversion from every MessagePack type.
+///
+/// Types that accept only some MessagePack types can implement `visitor::Visitor` instead.
+pub trait Deserializable: Sized + From<Option<Self>> + From<bool> + From<Binary> + From<f32> + From<f64> + From<u8> + From<u16> + From<u32> + From<u64> + From<i8> + From<i16> + From<i32> + From<i64> + From<String> + From<Vec<Self>> + From<BTreeMap<String, Self>> + From<Extension> + From<Timestamp> {
     fn deserialize<R: Read>(buf_reader: &mut R) -> Result<Self, DeserializeError> {
         match Marker::from(buf_reader.read_u8().or(Err(DeserializeError::InvalidMarker))?) {
             Marker::PositiveFixInt(n) => Ok(Self::from(n)),
@@ -100,21 +108,7 @@ pub trait Deserializable: Sized + From<Option<Self>> + From<bool> + From<Binary>
         let t = buf_reader.read_i8().or(Err(DeserializeError::InvalidLength))?;
 
         if t == -1 {
-            if size == 4 {
-                let value = buf_reader.read_u32::<BigEndian>().or(Err(DeserializeError::InvalidValue))?;
-                Utc.timestamp_opt(i64::from(value), 0).single().map(Self::from).ok_or(DeserializeError::InvalidValue)
-            } else if size == 8 {
-                let value = buf_reader.read_u64::<BigEndian>().or(Err(DeserializeError::InvalidValue))?;
-                let nano = value >> 34;
-                let sec = value & 0x00_00_00_03_ff_ff_ff_ff;
-                Utc.timestamp_opt(sec as i64, nano as u32).single().map(Self::from).ok_or(DeserializeError::InvalidValue)
-            } else if size == 12 {
-                let nano = buf_reader.read_u32::<BigEndian>().or(Err(DeserializeError::InvalidValue))?;
-                let sec = buf_reader.read_i64::<BigEndian>().or(Err(DeserializeError::InvalidValue))?;
-                Utc.timestamp_opt(sec, nano).single().map(Self::from).ok_or(DeserializeError::InvalidValue)
-            } else {
-                Err(DeserializeError::InvalidValue)
-            }
+            deserialize_timestamp(size, buf_reader).map(Self::from)
         } else {
             Self::deserialize_extension_for_the_you_type_defined(t, size, buf_reader)
         }
@@ -130,3 +124,13 @@ pub trait Deserializable: Sized + From<Option<Self>> + From<bool> + From<Binary>
         Ok(From::from(Extension { t, data }))
     }
 }
+
+/// Reads the `size`-byte payload of a timestamp extension, whose type byte has already been read.
+pub(crate) fn deserialize_timestamp<R: Read>(size: usize, buf_reader: &mut R) -> Result<Timestamp, DeserializeError> {
+    if !matches!(size, 4 | 8 | 12) {
+        return Err(DeserializeError::InvalidTimestampLength);
+    }
+    let mut data = vec![0; size];
+    buf_reader.read_exact(&mut data[..]).or(Err(DeserializeError::InvalidValue))?;
+    Timestamp::from_extension_data(&data)
+}
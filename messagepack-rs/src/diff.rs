@@ -0,0 +1,188 @@
+use crate::value::Value;
+use std::cmp::Ordering;
+use std::fmt;
+use std::mem::discriminant;
+
+/// One difference between two values, at the JSON Pointer `path`, which is empty for the root.
+///
+/// Removed array elements are addressed by their index in the old array and all other elements by their index in the new one.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added { path: String, value: Value },
+    Removed { path: String, value: Value },
+    /// A value of the same variant with different content.
+    Changed { path: String, old: Value, new: Value },
+    /// A value replaced by one of another variant, including another integer width.
+    TypeChanged { path: String, old: Value, new: Value },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } | Change::Changed { path, .. } | Change::TypeChanged { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = if self.path().is_empty() { "(root)" } else { self.path() };
+        match self {
+            Change::Added { value, .. } => write!(f, "+ {}: {:?}", path, value),
+            Change::Removed { value, .. } => write!(f, "- {}: {:?}", path, value),
+            Change::Changed { old, new, .. } => write!(f, "~ {}: {:?} -> {:?}", path, old, new),
+            Change::TypeChanged { old, new, .. } => write!(f, "~ {}: {:?} -> {:?} (type changed)", path, old, new),
+        }
+    }
+}
+
+/// Lists the changes that turn `old` into `new`, walking maps key by key and aligning arrays by their longest common subsequence.
+///
+/// Scalars are compared as `Value::total_cmp` does, so integers of different widths are a type change:
+/// normalize both values first to ignore them.
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_at(String::new(), old, new, &mut changes);
+    changes
+}
+
+/// Renders `changes` one per line, for example as the message of a failed assertion.
+pub fn render(changes: &[Change]) -> String {
+    changes.iter().map(|change| format!("{}\n", change)).collect()
+}
+
+fn diff_at(path: String, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Map(old), Value::Map(new)) => {
+            for (key, value) in old {
+                match new.get(key) {
+                    Some(new_value) => diff_at(child(&path, key), value, new_value, changes),
+                    None => changes.push(Change::Removed { path: child(&path, key), value: value.clone() }),
+                }
+            }
+            for (key, value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                changes.push(Change::Added { path: child(&path, key), value: value.clone() });
+            }
+        },
+        (Value::Array(old), Value::Array(new)) => diff_arrays(&path, old, new, changes),
+        _ if discriminant(old) != discriminant(new) => changes.push(Change::TypeChanged { path, old: old.clone(), new: new.clone() }),
+        _ if old.total_cmp(new) != Ordering::Equal => changes.push(Change::Changed { path, old: old.clone(), new: new.clone() }),
+        _ => (),
+    }
+}
+
+/// Elements outside the common subsequence between two matches are paired up and diffed in place,
+/// and the surplus of either side is reported as removed or added.
+fn diff_arrays(path: &str, old: &[Value], new: &[Value], changes: &mut Vec<Change>) {
+    let same = |a: &Value, b: &Value| a.total_cmp(b) == Ordering::Equal;
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if same(&old[i], &new[j]) { lengths[i + 1][j + 1] + 1 } else { lengths[i + 1][j].max(lengths[i][j + 1]) };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && same(&old[i], &new[j]) {
+            flush_gap(path, old, new, &mut removed, &mut added, changes);
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            removed.push(i);
+            i += 1;
+        } else {
+            added.push(j);
+            j += 1;
+        }
+    }
+    flush_gap(path, old, new, &mut removed, &mut added, changes);
+}
+
+fn flush_gap(path: &str, old: &[Value], new: &[Value], removed: &mut Vec<usize>, added: &mut Vec<usize>, changes: &mut Vec<Change>) {
+    let paired = removed.len().min(added.len());
+    for (&i, &j) in removed.iter().zip(added.iter()) {
+        diff_at(child(path, &j.to_string()), &old[i], &new[j], changes);
+    }
+    for &i in &removed[paired..] {
+        changes.push(Change::Removed { path: child(path, &i.to_string()), value: old[i].clone() });
+    }
+    for &j in &added[paired..] {
+        changes.push(Change::Added { path: child(path, &j.to_string()), value: new[j].clone() });
+    }
+    removed.clear();
+    added.clear();
+}
+
+/// Appends `token` to `path`, escaping it as a JSON Pointer reference token.
+fn child(path: &str, token: &str) -> String {
+    format!("{}/{}", path, token.replace('~', "~0").replace('/', "~1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn array(values: &[u8]) -> Value {
+        Value::from(values.to_vec())
+    }
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        entries.into_iter().collect()
+    }
+
+    #[test]
+    fn identical_values_have_no_changes() {
+        let value = map(vec![("a", array(&[1, 2])), ("b", Value::from("x"))]);
+        assert_eq!(diff(&value, &value.clone()), []);
+    }
+
+    #[test]
+    fn array_insertion() {
+        assert_eq!(diff(&array(&[1, 3]), &array(&[1, 2, 3])), [Change::Added { path: String::from("/1"), value: Value::UInt8(2) }]);
+    }
+
+    #[test]
+    fn array_deletion() {
+        assert_eq!(diff(&array(&[1, 2, 3]), &array(&[1, 3])), [Change::Removed { path: String::from("/1"), value: Value::UInt8(2) }]);
+    }
+
+    #[test]
+    fn array_replacement() {
+        assert_eq!(diff(&array(&[1, 2, 3]), &array(&[1, 4, 3])), [Change::Changed { path: String::from("/1"), old: Value::UInt8(2), new: Value::UInt8(4) }]);
+        assert_eq!(diff(&array(&[1, 2]), &Value::from(vec![Value::UInt8(1), Value::Int64(2)])), [
+            Change::TypeChanged { path: String::from("/1"), old: Value::UInt8(2), new: Value::Int64(2) },
+        ]);
+    }
+
+    #[test]
+    fn array_surplus_after_pairing() {
+        assert_eq!(diff(&array(&[1, 2, 9]), &array(&[3, 4, 5, 9])), [
+            Change::Changed { path: String::from("/0"), old: Value::UInt8(1), new: Value::UInt8(3) },
+            Change::Changed { path: String::from("/1"), old: Value::UInt8(2), new: Value::UInt8(4) },
+            Change::Added { path: String::from("/2"), value: Value::UInt8(5) },
+        ]);
+    }
+
+    #[test]
+    fn nested_map_changes() {
+        let old = map(vec![("a", map(vec![("b", Value::UInt8(1)), ("c", Value::UInt8(2))])), ("d", Value::Bool(true))]);
+        let new = map(vec![("a", map(vec![("b", Value::UInt8(5)), ("e", Value::UInt8(3))])), ("d", Value::Bool(true))]);
+        let changes = diff(&old, &new);
+        assert_eq!(changes, [
+            Change::Changed { path: String::from("/a/b"), old: Value::UInt8(1), new: Value::UInt8(5) },
+            Change::Removed { path: String::from("/a/c"), value: Value::UInt8(2) },
+            Change::Added { path: String::from("/a/e"), value: Value::UInt8(3) },
+        ]);
+        assert_eq!(render(&changes), "~ /a/b: UInt8(1) -> UInt8(5)\n- /a/c: UInt8(2)\n+ /a/e: UInt8(3)\n");
+    }
+
+    #[test]
+    fn keys_are_escaped_and_the_root_is_empty() {
+        let changes = diff(&map(vec![("x/y~", Value::Nil)]), &map(vec![]));
+        assert_eq!(changes, [Change::Removed { path: String::from("/x~1y~0"), value: Value::Nil }]);
+        assert_eq!(diff(&Value::Nil, &Value::Bool(false)), [Change::TypeChanged { path: String::new(), old: Value::Nil, new: Value::Bool(false) }]);
+    }
+}
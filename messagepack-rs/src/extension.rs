@@ -1,3 +1,8 @@
+pub mod field;
+pub mod registry;
+#[cfg(feature = "well-known-extensions")]
+pub mod well_known;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Extension {
     pub t: i8,
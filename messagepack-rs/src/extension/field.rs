@@ -0,0 +1,147 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use crate::binary::Binary;
+use crate::deserializable::DeserializeError;
+use crate::reader::Reader;
+use crate::serializable::{serialize_array_header, Serializable, SerializeError};
+use crate::value::Value;
+use std::convert::TryFrom;
+use std::io::Read;
+use std::mem::size_of;
+
+/// A field type that `#[derive(MessagePackExtension)]` can pack into extension data.
+///
+/// The fixed layout writes numbers big-endian at their natural width and prefixes strings and bytes with a `u32` length.
+/// The MessagePack layout writes each field as a MessagePack value.
+pub trait ExtensionField: Sized {
+    fn pack_fixed(&self, w: &mut Vec<u8>) -> Result<(), SerializeError>;
+    fn unpack_fixed(r: &mut &[u8]) -> Result<Self, DeserializeError>;
+    fn pack_msgpack(&self, w: &mut Vec<u8>) -> Result<(), SerializeError>;
+    fn unpack_msgpack(r: &mut Reader<&[u8]>) -> Result<Self, DeserializeError>;
+}
+
+impl ExtensionField for bool {
+    fn pack_fixed(&self, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+        w.write_u8(u8::from(*self)).or(Err(SerializeError::FailedToWrite))
+    }
+
+    fn unpack_fixed(r: &mut &[u8]) -> Result<Self, DeserializeError> {
+        match r.read_u8().or(Err(DeserializeError::InvalidLength))? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DeserializeError::InvalidValue),
+        }
+    }
+
+    fn pack_msgpack(&self, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+        w.append(&mut Value::serialize_bool(*self)?);
+        Ok(())
+    }
+
+    fn unpack_msgpack(r: &mut Reader<&[u8]>) -> Result<Self, DeserializeError> {
+        r.read_bool()
+    }
+}
+
+macro_rules! impl_extension_field_for_number {
+    ($($t:ty, $serialize:ident, $read:ident);+) => {
+        $(
+            impl ExtensionField for $t {
+                fn pack_fixed(&self, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+                    w.extend_from_slice(&self.to_be_bytes());
+                    Ok(())
+                }
+
+                fn unpack_fixed(r: &mut &[u8]) -> Result<Self, DeserializeError> {
+                    let mut buf = [0; size_of::<$t>()];
+                    r.read_exact(&mut buf).or(Err(DeserializeError::InvalidLength))?;
+                    Ok(<$t>::from_be_bytes(buf))
+                }
+
+                fn pack_msgpack(&self, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+                    w.append(&mut Value::$serialize(*self)?);
+                    Ok(())
+                }
+
+                fn unpack_msgpack(r: &mut Reader<&[u8]>) -> Result<Self, DeserializeError> {
+                    r.$read()
+                }
+            }
+        )+
+    };
+}
+
+impl_extension_field_for_number!(
+    u8, serialize_uint8, read_u8;
+    u16, serialize_uint16, read_u16;
+    u32, serialize_uint32, read_u32;
+    u64, serialize_uint64, read_u64;
+    i8, serialize_int8, read_i8;
+    i16, serialize_int16, read_i16;
+    i32, serialize_int32, read_i32;
+    i64, serialize_int64, read_i64;
+    f32, serialize_float32, read_f32;
+    f64, serialize_float64, read_f64
+);
+
+impl ExtensionField for String {
+    fn pack_fixed(&self, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+        pack_fixed_bytes(self.as_bytes(), w)
+    }
+
+    fn unpack_fixed(r: &mut &[u8]) -> Result<Self, DeserializeError> {
+        String::from_utf8(unpack_fixed_bytes(r)?).or(Err(DeserializeError::InvalidValue))
+    }
+
+    fn pack_msgpack(&self, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+        w.append(&mut Value::serialize_string(self.clone())?);
+        Ok(())
+    }
+
+    fn unpack_msgpack(r: &mut Reader<&[u8]>) -> Result<Self, DeserializeError> {
+        let mut buf = Vec::new();
+        Ok(String::from(r.read_str(&mut buf)?))
+    }
+}
+
+impl ExtensionField for Binary {
+    fn pack_fixed(&self, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+        pack_fixed_bytes(&self.0, w)
+    }
+
+    fn unpack_fixed(r: &mut &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Binary(unpack_fixed_bytes(r)?))
+    }
+
+    fn pack_msgpack(&self, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+        w.append(&mut Value::serialize_binary(self.clone())?);
+        Ok(())
+    }
+
+    fn unpack_msgpack(r: &mut Reader<&[u8]>) -> Result<Self, DeserializeError> {
+        let mut buf = Vec::new();
+        r.read_bin(&mut buf)?;
+        Ok(Binary(buf))
+    }
+}
+
+fn pack_fixed_bytes(v: &[u8], w: &mut Vec<u8>) -> Result<(), SerializeError> {
+    w.write_u32::<BigEndian>(u32::try_from(v.len()).or(Err(SerializeError::OutOfRange))?).or(Err(SerializeError::FailedToWrite))?;
+    w.extend_from_slice(v);
+    Ok(())
+}
+
+fn unpack_fixed_bytes(r: &mut &[u8]) -> Result<Vec<u8>, DeserializeError> {
+    let size = r.read_u32::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize;
+    if r.len() < size {
+        return Err(DeserializeError::InvalidLength);
+    }
+    let (bytes, rest) = r.split_at(size);
+    *r = rest;
+    Ok(bytes.to_vec())
+}
+
+/// Writes the array header that opens the fields of the MessagePack layout.
+pub fn pack_msgpack_fields_len(len: usize, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+    w.append(&mut serialize_array_header(len)?);
+    Ok(())
+}
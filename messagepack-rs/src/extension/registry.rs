@@ -0,0 +1,209 @@
+use crate::deserializable::DeserializeError;
+use crate::reader::{Header, Reader, MAX_PREALLOCATED};
+use crate::timestamp::Timestamp;
+use crate::value::Value;
+use crate::visitor::{map_key, Visitor};
+use super::Extension;
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+
+trait DynValue: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn encode(&self) -> Vec<u8>;
+    fn eq_dyn(&self, other: &dyn DynValue) -> bool;
+    fn fmt_dyn(&self, f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+struct Entry<T> {
+    value: T,
+    encode: fn(&T) -> Vec<u8>,
+}
+
+impl<T: Any + fmt::Debug + PartialEq + Send + Sync> DynValue for Entry<T> {
+    fn as_any(&self) -> &dyn Any {
+        &self.value
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        (self.encode)(&self.value)
+    }
+
+    fn eq_dyn(&self, other: &dyn DynValue) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(&self.value)
+    }
+
+    fn fmt_dyn(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// An extension decoded into an application type by an `ExtensionRegistry`.
+///
+/// It carries its own encoder, so it serializes back to the same extension without the registry.
+#[derive(Clone)]
+pub struct TypedExtension {
+    t: i8,
+    value: Arc<dyn DynValue>,
+}
+
+impl TypedExtension {
+    pub fn new<T: Any + fmt::Debug + PartialEq + Send + Sync>(t: i8, value: T, encode: fn(&T) -> Vec<u8>) -> Self {
+        TypedExtension { t, value: Arc::new(Entry { value, encode }) }
+    }
+
+    pub fn t(&self) -> i8 {
+        self.t
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.as_any().downcast_ref::<T>()
+    }
+
+    pub fn to_extension(&self) -> Extension {
+        Extension { t: self.t, data: self.value.encode() }
+    }
+}
+
+impl fmt::Debug for TypedExtension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TypedExtension {{ t: {}, value: ", self.t)?;
+        self.value.fmt_dyn(f)?;
+        write!(f, " }}")
+    }
+}
+
+impl PartialEq for TypedExtension {
+    fn eq(&self, other: &Self) -> bool {
+        self.t == other.t && self.value.eq_dyn(other.value.as_ref())
+    }
+}
+
+type Decoder = Box<dyn Fn(&[u8]) -> Result<TypedExtension, DeserializeError> + Send + Sync>;
+
+/// Codecs for extension types registered at runtime.
+///
+/// Extensions whose type is registered decode to `Value::TypedExtension`; all others stay `Value::Extension`.
+/// The timestamp type (-1) is always decoded by `Value` itself.
+#[derive(Default)]
+pub struct ExtensionRegistry {
+    decoders: HashMap<i8, (TypeId, Decoder)>,
+    encoders: HashMap<TypeId, (i8, Box<dyn Any + Send + Sync>)>,
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the codec for `T` under type `t`, replacing any codec previously registered for `t` or `T`.
+    pub fn register<T: Any + fmt::Debug + PartialEq + Send + Sync>(&mut self, t: i8, encode: fn(&T) -> Vec<u8>, decode: fn(&[u8]) -> Result<T, DeserializeError>) {
+        let id = TypeId::of::<T>();
+        // Drop both halves of the codecs being replaced, so no type keeps encoding to `t` and no decoder keeps producing `T`.
+        if let Some((previous, _)) = self.decoders.remove(&t) {
+            self.encoders.remove(&previous);
+        }
+        if let Some((previous, _)) = self.encoders.remove(&id) {
+            self.decoders.remove(&previous);
+        }
+        self.decoders.insert(t, (id, Box::new(move |data| Ok(TypedExtension::new(t, decode(data)?, encode)))));
+        self.encoders.insert(id, (t, Box::new(encode)));
+    }
+
+    pub fn is_registered(&self, t: i8) -> bool {
+        self.decoders.contains_key(&t)
+    }
+
+    /// Wraps `value` with the codec registered for `T`, or returns `None` if `T` is not registered.
+    pub fn wrap<T: Any + fmt::Debug + PartialEq + Send + Sync>(&self, value: T) -> Option<TypedExtension> {
+        let (t, encode) = self.encoders.get(&TypeId::of::<T>())?;
+        let encode = encode.downcast_ref::<fn(&T) -> Vec<u8>>()?;
+        Some(TypedExtension::new(*t, value, *encode))
+    }
+
+    /// Decodes `extension` with its registered codec, or returns `None` if its type is not registered.
+    pub fn decode(&self, extension: &Extension) -> Option<Result<TypedExtension, DeserializeError>> {
+        self.decoders.get(&extension.t).map(|(_, decode)| decode(&extension.data))
+    }
+
+    /// Deserializes one value, decoding extensions of registered types as they are read.
+    pub fn deserialize<R: Read>(&self, buf_reader: &mut R) -> Result<Value, DeserializeError> {
+        self.read(&mut Reader::new(buf_reader))
+    }
+
+    /// Reads one value like `Value::visit`, applying the reader's options, but decodes extensions of registered types as they are read.
+    pub fn read<R: Read>(&self, reader: &mut Reader<R>) -> Result<Value, DeserializeError> {
+        match reader.read_header()? {
+            Header::Ext(t, size) if t != Timestamp::EXTENSION_TYPE && self.is_registered(t) => {
+                let mut data = Vec::new();
+                reader.read_payload(size, &mut data)?;
+                let (_, decode) = &self.decoders[&t];
+                decode(&data).map(Value::TypedExtension)
+            },
+            Header::Array(len) => {
+                let mut buf = Vec::with_capacity(len.min(MAX_PREALLOCATED));
+                for _ in 0..len {
+                    buf.push(self.read(reader)?);
+                }
+                Ok(Value::Array(buf))
+            },
+            Header::Map(len) => {
+                let mut buf = BTreeMap::new();
+                for _ in 0..len {
+                    buf.insert(map_key(reader)?, self.read(reader)?);
+                }
+                Ok(Value::Map(buf))
+            },
+            header => Value::visit_with_header(header, reader),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct A(u8);
+
+    #[derive(Debug, PartialEq)]
+    struct B(u8);
+
+    fn registry() -> ExtensionRegistry {
+        let mut registry = ExtensionRegistry::new();
+        registry.register::<A>(5, |v| vec![v.0], |data| Ok(A(data[0])));
+        registry
+    }
+
+    #[test]
+    fn registering_another_type_under_the_same_id_unregisters_the_previous_type() {
+        let mut registry = registry();
+        registry.register::<B>(5, |v| vec![v.0], |data| Ok(B(data[0])));
+        assert!(registry.wrap(A(1)).is_none());
+        let decoded = registry.decode(&Extension { t: 5, data: vec![1] }).unwrap().unwrap();
+        assert_eq!(decoded.downcast_ref::<B>(), Some(&B(1)));
+    }
+
+    #[test]
+    fn registering_a_type_under_another_id_drops_its_previous_decoder() {
+        let mut registry = registry();
+        registry.register::<A>(6, |v| vec![v.0], |data| Ok(A(data[0])));
+        assert!(!registry.is_registered(5));
+        assert_eq!(registry.wrap(A(1)).unwrap().t(), 6);
+    }
+
+    #[test]
+    fn deserialize_decodes_registered_extensions_at_any_depth() {
+        // [{"a": ext 5 [1]}, ext 6 [2]]
+        let buf = [0x92, 0x81, 0xa1, b'a', 0xd4, 0x05, 0x01, 0xd4, 0x06, 0x02];
+        let value = registry().deserialize(&mut &buf[..]).unwrap();
+        let typed = registry().wrap(A(1)).unwrap();
+        let expected = Value::Array(vec![
+            Value::Map(vec![(String::from("a"), Value::TypedExtension(typed))].into_iter().collect()),
+            Value::Extension(Extension { t: 6, data: vec![2] }),
+        ]);
+        assert_eq!(value, expected);
+    }
+}
@@ -0,0 +1,514 @@
+use crate::deserializable::DeserializeError;
+use crate::timestamp::Timestamp;
+use crate::value::Value;
+use std::convert::TryFrom;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::time::Duration;
+use super::registry::{ExtensionRegistry, TypedExtension};
+use super::Extension;
+
+/// A type with a ready-made extension encoding whose type id is chosen by `WellKnownTypes`.
+pub trait WellKnownExtension: Sized + fmt::Debug + PartialEq + Send + Sync + 'static {
+    fn t(types: &WellKnownTypes) -> i8;
+    fn encode(&self) -> Vec<u8>;
+    fn decode(data: &[u8]) -> Result<Self, DeserializeError>;
+}
+
+/// Type ids used for the well-known extensions. Each can be changed to avoid clashing with application types.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WellKnownTypes {
+    pub uuid: i8,
+    pub big_int: i8,
+    pub decimal: i8,
+    pub duration: i8,
+    pub ip_addr: i8,
+    pub offset_timestamp: i8,
+}
+
+impl Default for WellKnownTypes {
+    fn default() -> Self {
+        WellKnownTypes { uuid: 100, big_int: 101, decimal: 102, duration: 103, ip_addr: 104, offset_timestamp: 105 }
+    }
+}
+
+impl WellKnownTypes {
+    pub fn register(&self, registry: &mut ExtensionRegistry) {
+        registry.register(self.uuid, Uuid::encode, Uuid::decode);
+        registry.register(self.big_int, BigInt::encode, BigInt::decode);
+        registry.register(self.decimal, Decimal::encode, Decimal::decode);
+        registry.register(self.duration, Duration::encode, Duration::decode);
+        registry.register(self.ip_addr, IpAddr::encode, IpAddr::decode);
+        registry.register(self.offset_timestamp, OffsetTimestamp::encode, OffsetTimestamp::decode);
+    }
+
+    /// Returns a registry with every well-known extension registered.
+    pub fn registry(&self) -> ExtensionRegistry {
+        let mut registry = ExtensionRegistry::new();
+        self.register(&mut registry);
+        registry
+    }
+
+    pub fn to_extension<T: WellKnownExtension>(&self, value: &T) -> Extension {
+        Extension { t: T::t(self), data: value.encode() }
+    }
+
+    pub fn to_value<T: WellKnownExtension>(&self, value: T) -> Value {
+        Value::TypedExtension(TypedExtension::new(T::t(self), value, T::encode))
+    }
+}
+
+/// A UUID stored as its 16 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Uuid(pub [u8; 16]);
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if i == 4 || i == 6 || i == 8 || i == 10 {
+                write!(f, "-")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Uuid {
+    type Err = DeserializeError;
+
+    /// Accepts 32 hex digits, optionally hyphenated as 8-4-4-4-12.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hyphenated = match s.len() {
+            32 => false,
+            36 => true,
+            _ => return Err(DeserializeError::InvalidLength),
+        };
+        let mut digits = Vec::with_capacity(32);
+        for (i, c) in s.chars().enumerate() {
+            if hyphenated && matches!(i, 8 | 13 | 18 | 23) {
+                if c != '-' {
+                    return Err(DeserializeError::InvalidValue);
+                }
+            } else {
+                digits.push(c.to_digit(16).ok_or(DeserializeError::InvalidValue)? as u8);
+            }
+        }
+        let mut bytes = [0; 16];
+        for (b, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+            *b = pair[0] << 4 | pair[1];
+        }
+        Ok(Uuid(bytes))
+    }
+}
+
+impl WellKnownExtension for Uuid {
+    fn t(types: &WellKnownTypes) -> i8 {
+        types.uuid
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, DeserializeError> {
+        <[u8; 16]>::try_from(data).map(Uuid).or(Err(DeserializeError::InvalidLength))
+    }
+}
+
+/// An arbitrary-precision integer stored as minimal big-endian two's complement bytes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigInt(Vec<u8>);
+
+impl BigInt {
+    /// Builds an integer from big-endian two's complement bytes. An empty slice is zero.
+    pub fn from_signed_bytes_be(bytes: &[u8]) -> Self {
+        let mut bytes = bytes.to_vec();
+        if bytes.is_empty() {
+            bytes.push(0);
+        }
+        trim(&mut bytes);
+        BigInt(bytes)
+    }
+
+    pub fn to_signed_bytes_be(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0[0] & 0x80 != 0
+    }
+
+    fn from_magnitude(negative: bool, magnitude: &[u8]) -> Self {
+        let mut bytes = Vec::with_capacity(1 + magnitude.len());
+        bytes.push(0);
+        bytes.extend_from_slice(magnitude);
+        if negative {
+            negate(&mut bytes);
+        }
+        trim(&mut bytes);
+        BigInt(bytes)
+    }
+
+    /// Big-endian bytes of the absolute value.
+    fn magnitude(&self) -> Vec<u8> {
+        let mut bytes = self.0.clone();
+        if self.is_negative() {
+            negate(&mut bytes);
+        }
+        bytes
+    }
+}
+
+/// Negates big-endian two's complement bytes in place.
+fn negate(bytes: &mut [u8]) {
+    let mut carry = true;
+    for b in bytes.iter_mut().rev() {
+        let (v, overflow) = (!*b).overflowing_add(u8::from(carry));
+        *b = v;
+        carry = overflow;
+    }
+}
+
+/// Removes leading bytes that only repeat the sign.
+fn trim(bytes: &mut Vec<u8>) {
+    let redundant = bytes.windows(2).take_while(|w| (w[0] == 0 && w[1] & 0x80 == 0) || (w[0] == 0xff && w[1] & 0x80 != 0)).count();
+    bytes.drain(..redundant);
+}
+
+impl From<i64> for BigInt {
+    fn from(v: i64) -> Self {
+        Self::from(i128::from(v))
+    }
+}
+
+impl From<u64> for BigInt {
+    fn from(v: u64) -> Self {
+        Self::from(u128::from(v))
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(v: i128) -> Self {
+        Self::from_signed_bytes_be(&v.to_be_bytes())
+    }
+}
+
+impl From<u128> for BigInt {
+    fn from(v: u128) -> Self {
+        Self::from_magnitude(false, &v.to_be_bytes())
+    }
+}
+
+impl TryFrom<&BigInt> for i128 {
+    type Error = DeserializeError;
+
+    fn try_from(v: &BigInt) -> Result<Self, Self::Error> {
+        if v.0.len() > 16 {
+            return Err(DeserializeError::OutOfRange);
+        }
+        let mut bytes = if v.is_negative() { [0xff; 16] } else { [0; 16] };
+        bytes[16 - v.0.len()..].copy_from_slice(&v.0);
+        Ok(i128::from_be_bytes(bytes))
+    }
+}
+
+impl TryFrom<&BigInt> for u128 {
+    type Error = DeserializeError;
+
+    fn try_from(v: &BigInt) -> Result<Self, Self::Error> {
+        let bytes = if v.0.len() == 17 && v.0[0] == 0 { &v.0[1..] } else { &v.0[..] };
+        if v.is_negative() || bytes.len() > 16 {
+            return Err(DeserializeError::OutOfRange);
+        }
+        let mut buf = [0; 16];
+        buf[16 - bytes.len()..].copy_from_slice(bytes);
+        Ok(u128::from_be_bytes(buf))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut magnitude = self.magnitude();
+        let mut digits = Vec::new();
+        while magnitude.iter().any(|&b| b != 0) {
+            let mut remainder = 0u16;
+            for b in magnitude.iter_mut() {
+                let v = (remainder << 8) | u16::from(*b);
+                *b = (v / 10) as u8;
+                remainder = v % 10;
+            }
+            digits.push(b'0' + remainder as u8);
+        }
+        if digits.is_empty() {
+            digits.push(b'0');
+        }
+        if self.is_negative() {
+            digits.push(b'-');
+        }
+        digits.reverse();
+        f.write_str(std::str::from_utf8(&digits).map_err(|_| fmt::Error)?)
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = DeserializeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        if digits.is_empty() {
+            return Err(DeserializeError::InvalidValue);
+        }
+        let mut magnitude = vec![0u8];
+        for c in digits.bytes() {
+            if !c.is_ascii_digit() {
+                return Err(DeserializeError::InvalidValue);
+            }
+            let mut carry = u16::from(c - b'0');
+            for b in magnitude.iter_mut().rev() {
+                let v = u16::from(*b) * 10 + carry;
+                *b = v as u8;
+                carry = v >> 8;
+            }
+            if carry > 0 {
+                magnitude.insert(0, carry as u8);
+            }
+        }
+        Ok(Self::from_magnitude(negative, &magnitude))
+    }
+}
+
+impl WellKnownExtension for BigInt {
+    fn t(types: &WellKnownTypes) -> i8 {
+        types.big_int
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, DeserializeError> {
+        if data.is_empty() {
+            return Err(DeserializeError::InvalidLength);
+        }
+        Ok(Self::from_signed_bytes_be(data))
+    }
+}
+
+/// An arbitrary-precision decimal equal to `unscaled * 10^-scale`.
+///
+/// Encoded as the scale (`i32`, big-endian) followed by the unscaled value as a `BigInt`.
+///
+/// Decoding and parsing fail with `OutOfRange` when the scale exceeds `MAX_SCALE` in either direction,
+/// since formatting such a decimal writes that many digits.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    pub unscaled: BigInt,
+    pub scale: i32,
+}
+
+impl Decimal {
+    pub const MAX_SCALE: u32 = 65_535;
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unscaled = self.unscaled.to_string();
+        let (sign, digits) = match unscaled.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", &unscaled[..]),
+        };
+        if self.scale <= 0 {
+            return write!(f, "{}{}{}", sign, digits, "0".repeat(self.scale.unsigned_abs() as usize));
+        }
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int, frac)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = DeserializeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (int, frac) = match s.find('.') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, ""),
+        };
+        let scale = i32::try_from(frac.len()).ok().filter(|scale| scale.unsigned_abs() <= Self::MAX_SCALE).ok_or(DeserializeError::OutOfRange)?;
+        Ok(Decimal { unscaled: format!("{}{}", int, frac).parse()?, scale })
+    }
+}
+
+impl WellKnownExtension for Decimal {
+    fn t(types: &WellKnownTypes) -> i8 {
+        types.decimal
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data = self.scale.to_be_bytes().to_vec();
+        data.extend_from_slice(&self.unscaled.0);
+        data
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, DeserializeError> {
+        if data.len() < 4 + 1 {
+            return Err(DeserializeError::InvalidLength);
+        }
+        let (scale, unscaled) = data.split_at(4);
+        let scale = i32::from_be_bytes([scale[0], scale[1], scale[2], scale[3]]);
+        if scale.unsigned_abs() > Self::MAX_SCALE {
+            return Err(DeserializeError::OutOfRange);
+        }
+        Ok(Decimal { unscaled: BigInt::from_signed_bytes_be(unscaled), scale })
+    }
+}
+
+/// Encoded as the seconds (`u64`) followed by the nanoseconds (`u32`), both big-endian.
+impl WellKnownExtension for Duration {
+    fn t(types: &WellKnownTypes) -> i8 {
+        types.duration
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data = self.as_secs().to_be_bytes().to_vec();
+        data.extend_from_slice(&self.subsec_nanos().to_be_bytes());
+        data
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, DeserializeError> {
+        let data = <[u8; 12]>::try_from(data).or(Err(DeserializeError::InvalidLength))?;
+        let secs = u64::from_be_bytes([data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7]]);
+        let nanos = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+        if nanos >= 1_000_000_000 {
+            return Err(DeserializeError::InvalidValue);
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+}
+
+/// Encoded as the 4 octets of an IPv4 address or the 16 octets of an IPv6 address.
+impl WellKnownExtension for IpAddr {
+    fn t(types: &WellKnownTypes) -> i8 {
+        types.ip_addr
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            IpAddr::V4(v) => v.octets().to_vec(),
+            IpAddr::V6(v) => v.octets().to_vec(),
+        }
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, DeserializeError> {
+        if let Ok(octets) = <[u8; 4]>::try_from(data) {
+            Ok(IpAddr::V4(Ipv4Addr::from(octets)))
+        } else if let Ok(octets) = <[u8; 16]>::try_from(data) {
+            Ok(IpAddr::V6(Ipv6Addr::from(octets)))
+        } else {
+            Err(DeserializeError::InvalidLength)
+        }
+    }
+}
+
+/// A timestamp together with the UTC offset, in seconds east of UTC, of the clock that produced it.
+///
+/// Converts to and from `chrono::DateTime<FixedOffset>` (feature `chrono`) and `time::OffsetDateTime` (feature `time`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OffsetTimestamp {
+    pub timestamp: Timestamp,
+    pub offset_seconds: i32,
+}
+
+/// Encoded as the data of a 96-bit timestamp followed by the offset (`i32`, big-endian), 16 bytes in total.
+impl WellKnownExtension for OffsetTimestamp {
+    fn t(types: &WellKnownTypes) -> i8 {
+        types.offset_timestamp
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data = [&self.timestamp.nanos.to_be_bytes()[..], &self.timestamp.seconds.to_be_bytes()[..]].concat();
+        data.extend_from_slice(&self.offset_seconds.to_be_bytes());
+        data
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, DeserializeError> {
+        if data.len() != 16 {
+            return Err(DeserializeError::InvalidLength);
+        }
+        let (timestamp, offset) = data.split_at(12);
+        let offset_seconds = i32::from_be_bytes([offset[0], offset[1], offset[2], offset[3]]);
+        if offset_seconds.unsigned_abs() >= 86_400 {
+            return Err(DeserializeError::InvalidValue);
+        }
+        Ok(OffsetTimestamp { timestamp: Timestamp::from_extension_data(timestamp)?, offset_seconds })
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::FixedOffset>> for OffsetTimestamp {
+    fn from(value: chrono::DateTime<chrono::FixedOffset>) -> Self {
+        OffsetTimestamp { timestamp: Timestamp::from(value.with_timezone(&chrono::Utc)), offset_seconds: value.offset().local_minus_utc() }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<OffsetTimestamp> for chrono::DateTime<chrono::FixedOffset> {
+    type Error = DeserializeError;
+
+    fn try_from(value: OffsetTimestamp) -> Result<Self, Self::Error> {
+        let offset = chrono::FixedOffset::east_opt(value.offset_seconds).ok_or(DeserializeError::OutOfRange)?;
+        Ok(chrono::DateTime::<chrono::Utc>::try_from(value.timestamp)?.with_timezone(&offset))
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for OffsetTimestamp {
+    fn from(value: time::OffsetDateTime) -> Self {
+        OffsetTimestamp { timestamp: Timestamp::from(value), offset_seconds: value.offset().whole_seconds() }
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<OffsetTimestamp> for time::OffsetDateTime {
+    type Error = DeserializeError;
+
+    fn try_from(value: OffsetTimestamp) -> Result<Self, Self::Error> {
+        let offset = time::UtcOffset::from_whole_seconds(value.offset_seconds).or(Err(DeserializeError::OutOfRange))?;
+        time::OffsetDateTime::try_from(value.timestamp)?.checked_to_offset(offset).ok_or(DeserializeError::OutOfRange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uuid_from_str_accepts_only_canonical_forms() {
+        let expected = Uuid([0x12, 0x3e, 0x45, 0x67, 0xe8, 0x9b, 0x12, 0xd3, 0xa4, 0x56, 0x42, 0x66, 0x14, 0x17, 0x40, 0x00]);
+        assert_eq!(Uuid::from_str("123e4567-e89b-12d3-a456-426614174000").unwrap(), expected);
+        assert_eq!(Uuid::from_str("123E4567E89B12D3A456426614174000").unwrap(), expected);
+        assert!(Uuid::from_str("+23e4567-e89b-12d3-a456-426614174000").is_err());
+        assert!(Uuid::from_str("123e4567e-89b-12d3-a456-426614174000").is_err());
+        assert!(Uuid::from_str("123e4567-e89b-12d3-a456-42661417400-").is_err());
+        assert!(Uuid::from_str("123e4567-e89b-12d3-a456-4266141740").is_err());
+        assert!(Uuid::from_str("+23e4567e89b12d3a456426614174000").is_err());
+    }
+
+    #[test]
+    fn decimal_decode_rejects_huge_scales() {
+        for scale in &[i32::MIN, i32::MAX, Decimal::MAX_SCALE as i32 + 1, -(Decimal::MAX_SCALE as i32) - 1] {
+            let mut data = scale.to_be_bytes().to_vec();
+            data.push(1);
+            assert!(matches!(Decimal::decode(&data), Err(DeserializeError::OutOfRange)));
+        }
+        let mut data = (-3i32).to_be_bytes().to_vec();
+        data.push(1);
+        assert_eq!(Decimal::decode(&data).unwrap().to_string(), "1000");
+    }
+}
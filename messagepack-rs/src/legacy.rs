@@ -0,0 +1,86 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use crate::marker::Marker;
+use crate::serializable::{serialize_array_header, serialize_map_header, Serializable, SerializeError};
+use crate::value::Value;
+
+/// Serializes `value` in the raw format of the MessagePack spec before 2013, for consumers that only implement it.
+///
+/// Strings and binaries are both written as raw, using only the FixStr, Str16 and Str32 markers,
+/// and extensions, including timestamps, fail with `Unsupported`.
+/// Such bytes can be read back with the `str_as_binary` reader option.
+/// A custom value enum converts into `Value` through its `TryFrom` conversion first.
+pub fn serialize<V: Into<Value>>(value: V) -> Result<Vec<u8>, SerializeError> {
+    let mut w = Vec::new();
+    write(value.into(), &mut w)?;
+    Ok(w)
+}
+
+fn write(value: Value, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+    match value {
+        Value::Binary(v) => write_raw(&v.0, w),
+        Value::String(v) => write_raw(v.as_bytes(), w),
+        Value::NonUtf8String(v) => write_raw(&v, w),
+        Value::Array(v) => {
+            w.extend_from_slice(&serialize_array_header(v.len())?);
+            v.into_iter().try_for_each(|v| write(v, w))
+        },
+        Value::Map(v) => {
+            w.extend_from_slice(&serialize_map_header(v.len())?);
+            v.into_iter().try_for_each(|(k, v)| {
+                write_raw(k.as_bytes(), w)?;
+                write(v, w)
+            })
+        },
+        Value::Extension(_) | Value::Timestamp(_) | Value::FormattedTimestamp(_, _) | Value::TypedExtension(_) => Err(SerializeError::Unsupported),
+        v => {
+            w.extend_from_slice(&v.serialize()?);
+            Ok(())
+        },
+    }
+}
+
+fn write_raw(v: &[u8], w: &mut Vec<u8>) -> Result<(), SerializeError> {
+    match v.len() {
+        len if len <= 31 => w.write_u8(Marker::FixStr(len as u8).into()),
+        len if u16::MAX as usize >= len => w.write_u8(Marker::Str16.into()).and_then(|_| w.write_u16::<BigEndian>(len as u16)),
+        len if u32::MAX as usize >= len => w.write_u8(Marker::Str32.into()).and_then(|_| w.write_u32::<BigEndian>(len as u32)),
+        _ => return Err(SerializeError::OutOfRange),
+    }.or(Err(SerializeError::FailedToWrite))?;
+    w.extend_from_slice(v);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::Binary;
+    use crate::extension::Extension;
+    use crate::timestamp::Timestamp;
+
+    #[test]
+    fn str8_becomes_str16() {
+        let s = "a".repeat(32);
+        assert_eq!(Value::from(s.as_str()).serialize().unwrap()[..2], [0xd9, 0x20]);
+        assert_eq!(serialize(s.as_str()).unwrap(), [&[0xda, 0x00, 0x20][..], s.as_bytes()].concat());
+        assert_eq!(serialize("abc").unwrap(), [0xa3, b'a', b'b', b'c']);
+    }
+
+    #[test]
+    fn bin_becomes_raw() {
+        assert_eq!(serialize(Binary(vec![0xff, 0x00])).unwrap(), [0xa2, 0xff, 0x00]);
+        assert_eq!(serialize(Binary(vec![0x01; 40])).unwrap(), [&[0xda, 0x00, 0x28][..], &[0x01; 40][..]].concat());
+    }
+
+    #[test]
+    fn containers_and_scalars_keep_their_encoding() {
+        let value = Value::from((1u8, Binary(vec![0x02]), vec![("k", Value::Nil)].into_iter().collect::<Value>(), true));
+        assert_eq!(serialize(value).unwrap(), [0x94, 0x01, 0xa1, 0x02, 0x81, 0xa1, b'k', 0xc0, 0xc3]);
+    }
+
+    #[test]
+    fn extensions_are_unsupported() {
+        assert!(matches!(serialize(Extension { t: 1, data: vec![0x00] }), Err(SerializeError::Unsupported)));
+        assert!(matches!(serialize(Timestamp::new(1, 0)), Err(SerializeError::Unsupported)));
+        assert!(matches!(serialize(Value::from(vec![Value::Nil, Value::from(Extension { t: 1, data: vec![] })])), Err(SerializeError::Unsupported)));
+    }
+}
@@ -1,11 +1,20 @@
 #![forbid(unsafe_code)]
 
 pub mod binary;
+pub mod decodable;
 pub mod deserializable;
+pub mod diff;
 pub mod extension;
+pub mod legacy;
+pub mod marked;
 pub mod marker;
+pub mod pointer;
+pub mod reader;
+pub mod repack;
 pub mod serializable;
 pub mod stream;
+pub mod timestamp;
 pub mod value;
+pub mod visitor;
 
 pub use crate::marker::Marker;
@@ -0,0 +1,324 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use crate::binary::Binary;
+use crate::deserializable::DeserializeError;
+use crate::extension::Extension;
+use crate::marker::Marker;
+use crate::reader::{Header, Reader, MAX_PREALLOCATED};
+use crate::serializable::{Serializable, SerializeError};
+use crate::value::Value;
+use std::io::Read;
+use std::mem::discriminant;
+
+/// A decoded value that remembers the marker of each node, so that it serializes back to the same bytes
+/// even when the producer did not use the smallest encoding.
+///
+/// When the `node` of a decoded value is replaced, its marker is kept as long as it can still hold the new value,
+/// otherwise, and for nodes without a marker, the smallest marker is used.
+/// A proxy that changes one field therefore leaves every other byte identical.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marked {
+    pub marker: Option<Marker>,
+    pub node: Node,
+}
+
+/// The content of a `Marked` value. Maps keep their entries in order and accept keys of any type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Nil,
+    Bool(bool),
+    UInt(u64),
+    Int(i64),
+    Float32(f32),
+    Float64(f64),
+    Binary(Binary),
+    String(String),
+    Array(Vec<Marked>),
+    Map(Vec<(Marked, Marked)>),
+    Extension(Extension),
+}
+
+const INT_MARKERS: [Marker; 10] = [
+    Marker::PositiveFixInt(0), Marker::NegativeFixInt(-1),
+    Marker::UInt8, Marker::Int8, Marker::UInt16, Marker::Int16, Marker::UInt32, Marker::Int32, Marker::UInt64, Marker::Int64,
+];
+const STR_MARKERS: [Marker; 4] = [Marker::FixStr(0), Marker::Str8, Marker::Str16, Marker::Str32];
+const BIN_MARKERS: [Marker; 3] = [Marker::Bin8, Marker::Bin16, Marker::Bin32];
+const EXT_MARKERS: [Marker; 8] = [
+    Marker::FixExt1, Marker::FixExt2, Marker::FixExt4, Marker::FixExt8, Marker::FixExt16, Marker::Ext8, Marker::Ext16, Marker::Ext32,
+];
+const ARRAY_MARKERS: [Marker; 3] = [Marker::FixArray(0), Marker::Array16, Marker::Array32];
+const MAP_MARKERS: [Marker; 3] = [Marker::FixMap(0), Marker::Map16, Marker::Map32];
+
+impl Marked {
+    /// A value without a marker, which serializes with the smallest one.
+    pub fn new(node: Node) -> Self {
+        Marked { marker: None, node }
+    }
+
+    pub fn deserialize<R: Read>(buf_reader: &mut R) -> Result<Self, DeserializeError> {
+        Self::read(&mut Reader::new(buf_reader))
+    }
+
+    pub fn read<R: Read>(reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        let (marker, header) = reader.read_header_with_marker()?;
+        let node = match header {
+            Header::Nil => Node::Nil,
+            Header::Bool(v) => Node::Bool(v),
+            Header::UInt(v) => Node::UInt(v),
+            Header::Int(v) => Node::Int(v),
+            Header::Float32(v) => Node::Float32(v),
+            Header::Float64(v) => Node::Float64(v),
+            Header::Str(size) => {
+                let mut buf = Vec::new();
+                reader.read_payload(size, &mut buf)?;
+                Node::String(String::from_utf8(buf).or(Err(DeserializeError::InvalidValue))?)
+            },
+            Header::Bin(size) => {
+                let mut buf = Vec::new();
+                reader.read_payload(size, &mut buf)?;
+                Node::Binary(Binary(buf))
+            },
+            Header::Ext(t, size) => {
+                let mut data = Vec::new();
+                reader.read_payload(size, &mut data)?;
+                Node::Extension(Extension { t, data })
+            },
+            Header::Array(len) => {
+                let mut buf = Vec::with_capacity(len.min(MAX_PREALLOCATED));
+                for _ in 0..len {
+                    buf.push(Self::read(reader)?);
+                }
+                Node::Array(buf)
+            },
+            Header::Map(len) => {
+                let mut buf = Vec::with_capacity(len.min(MAX_PREALLOCATED));
+                for _ in 0..len {
+                    buf.push((Self::read(reader)?, Self::read(reader)?));
+                }
+                Node::Map(buf)
+            },
+        };
+        Ok(Marked { marker: Some(marker), node })
+    }
+
+    /// Converts `value` through its serialized form, so every node gets the marker `Value` would write.
+    pub fn from_value(value: Value) -> Result<Self, SerializeError> {
+        Self::deserialize(&mut &value.serialize()?[..]).or(Err(SerializeError::FailedToWrite))
+    }
+
+    /// Returns the value at the JSON Pointer `pointer`, matching map keys against string keys only.
+    pub fn pointer(&self, pointer: &str) -> Option<&Marked> {
+        let mut target = self;
+        for token in crate::pointer::parse(pointer).ok()? {
+            target = match &target.node {
+                Node::Array(v) => v.get(crate::pointer::parse_index(&token)?)?,
+                Node::Map(v) => v.iter().find(|(k, _)| k.node == Node::String(token.clone())).map(|(_, v)| v)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Mutable version of `pointer`. Assign to `node` of the result to keep its marker.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Marked> {
+        let mut target = self;
+        for token in crate::pointer::parse(pointer).ok()? {
+            target = match &mut target.node {
+                Node::Array(v) => v.get_mut(crate::pointer::parse_index(&token)?)?,
+                Node::Map(v) => v.iter_mut().find(|(k, _)| k.node == Node::String(token.clone())).map(|(_, v)| v)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    fn write(&self, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+        let header = match &self.node {
+            Node::Nil => Header::Nil,
+            Node::Bool(v) => Header::Bool(*v),
+            Node::UInt(v) => Header::UInt(*v),
+            Node::Int(v) => Header::Int(*v),
+            Node::Float32(v) => Header::Float32(*v),
+            Node::Float64(v) => Header::Float64(*v),
+            Node::Binary(v) => Header::Bin(v.0.len()),
+            Node::String(v) => Header::Str(v.len()),
+            Node::Array(v) => Header::Array(v.len()),
+            Node::Map(v) => Header::Map(v.len()),
+            Node::Extension(v) => Header::Ext(v.t, v.data.len()),
+        };
+        write_header(self.marker.as_ref(), header, w)?;
+        match &self.node {
+            Node::Binary(v) => w.extend_from_slice(&v.0),
+            Node::String(v) => w.extend_from_slice(v.as_bytes()),
+            Node::Extension(v) => w.extend_from_slice(&v.data),
+            Node::Array(v) => v.iter().try_for_each(|v| v.write(w))?,
+            Node::Map(v) => v.iter().try_for_each(|(k, v)| k.write(w).and_then(|_| v.write(w)))?,
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+impl Serializable for Marked {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        let mut w = Vec::new();
+        self.write(&mut w)?;
+        Ok(w)
+    }
+}
+
+/// Writes `header` with the `recorded` marker when it can still hold the value or length, or else with the smallest marker.
+pub(crate) fn write_header(recorded: Option<&Marker>, header: Header, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+    match header {
+        Header::Nil => w.write_u8(Marker::Nil.into()).or(Err(SerializeError::FailedToWrite)),
+        Header::Bool(v) => w.write_u8(if v { Marker::True } else { Marker::False }.into()).or(Err(SerializeError::FailedToWrite)),
+        Header::UInt(v) => write_int(recorded, i128::from(v), w),
+        Header::Int(v) => write_int(recorded, i128::from(v), w),
+        Header::Float32(v) => w.write_u8(Marker::Float32.into()).and_then(|_| w.write_f32::<BigEndian>(v)).or(Err(SerializeError::FailedToWrite)),
+        Header::Float64(v) => w.write_u8(Marker::Float64.into()).and_then(|_| w.write_f64::<BigEndian>(v)).or(Err(SerializeError::FailedToWrite)),
+        Header::Str(len) => write_length_header(length_marker(recorded, &STR_MARKERS, len)?, len, w),
+        Header::Bin(len) => write_length_header(length_marker(recorded, &BIN_MARKERS, len)?, len, w),
+        Header::Array(len) => write_length_header(length_marker(recorded, &ARRAY_MARKERS, len)?, len, w),
+        Header::Map(len) => write_length_header(length_marker(recorded, &MAP_MARKERS, len)?, len, w),
+        Header::Ext(t, len) => {
+            write_length_header(length_marker(recorded, &EXT_MARKERS, len)?, len, w)?;
+            w.write_i8(t).or(Err(SerializeError::FailedToWrite))
+        },
+    }
+}
+
+/// Picks `recorded` when it belongs to `family` and `holds` accepts it, or else the first marker of `family` that does.
+fn pick<F: Fn(&Marker) -> bool>(recorded: Option<&Marker>, family: &[Marker], holds: F) -> Option<Marker> {
+    recorded
+        .filter(|m| family.iter().any(|f| discriminant(f) == discriminant(*m)) && holds(m))
+        .or_else(|| family.iter().find(|m| holds(m)))
+        .cloned()
+}
+
+fn holds_int(marker: &Marker, v: i128) -> bool {
+    let (min, max) = match marker {
+        Marker::PositiveFixInt(_) => (0, 0x7f),
+        Marker::NegativeFixInt(_) => (-32, -1),
+        Marker::UInt8 => (0, i128::from(u8::MAX)),
+        Marker::UInt16 => (0, i128::from(u16::MAX)),
+        Marker::UInt32 => (0, i128::from(u32::MAX)),
+        Marker::UInt64 => (0, i128::from(u64::MAX)),
+        Marker::Int8 => (i128::from(i8::MIN), i128::from(i8::MAX)),
+        Marker::Int16 => (i128::from(i16::MIN), i128::from(i16::MAX)),
+        Marker::Int32 => (i128::from(i32::MIN), i128::from(i32::MAX)),
+        Marker::Int64 => (i128::from(i64::MIN), i128::from(i64::MAX)),
+        _ => return false,
+    };
+    min <= v && v <= max
+}
+
+fn write_int(recorded: Option<&Marker>, v: i128, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+    match pick(recorded, &INT_MARKERS, |m| holds_int(m, v)).ok_or(SerializeError::OutOfRange)? {
+        Marker::PositiveFixInt(_) => w.write_u8(Marker::PositiveFixInt(v as u8).into()),
+        Marker::NegativeFixInt(_) => w.write_u8(Marker::NegativeFixInt(v as i8).into()),
+        marker => {
+            w.write_u8(marker.clone().into()).or(Err(SerializeError::FailedToWrite))?;
+            match marker {
+                Marker::UInt8 => w.write_u8(v as u8),
+                Marker::UInt16 => w.write_u16::<BigEndian>(v as u16),
+                Marker::UInt32 => w.write_u32::<BigEndian>(v as u32),
+                Marker::UInt64 => w.write_u64::<BigEndian>(v as u64),
+                Marker::Int8 => w.write_i8(v as i8),
+                Marker::Int16 => w.write_i16::<BigEndian>(v as i16),
+                Marker::Int32 => w.write_i32::<BigEndian>(v as i32),
+                _ => w.write_i64::<BigEndian>(v as i64),
+            }
+        },
+    }.or(Err(SerializeError::FailedToWrite))
+}
+
+fn holds_len(marker: &Marker, len: usize) -> bool {
+    match marker {
+        Marker::FixStr(_) => len <= 31,
+        Marker::FixArray(_) | Marker::FixMap(_) => len <= 15,
+        Marker::FixExt1 => len == 1,
+        Marker::FixExt2 => len == 2,
+        Marker::FixExt4 => len == 4,
+        Marker::FixExt8 => len == 8,
+        Marker::FixExt16 => len == 16,
+        Marker::Str8 | Marker::Bin8 | Marker::Ext8 => len <= u8::MAX as usize,
+        Marker::Str16 | Marker::Bin16 | Marker::Ext16 | Marker::Array16 | Marker::Map16 => len <= u16::MAX as usize,
+        Marker::Str32 | Marker::Bin32 | Marker::Ext32 | Marker::Array32 | Marker::Map32 => len <= u32::MAX as usize,
+        _ => false,
+    }
+}
+
+fn length_marker(recorded: Option<&Marker>, family: &[Marker], len: usize) -> Result<Marker, SerializeError> {
+    Ok(match pick(recorded, family, |m| holds_len(m, len)).ok_or(SerializeError::OutOfRange)? {
+        Marker::FixStr(_) => Marker::FixStr(len as u8),
+        Marker::FixArray(_) => Marker::FixArray(len as u8),
+        Marker::FixMap(_) => Marker::FixMap(len as u8),
+        marker => marker,
+    })
+}
+
+/// Writes `marker` followed by `len` at the width the marker calls for.
+fn write_length_header(marker: Marker, len: usize, w: &mut Vec<u8>) -> Result<(), SerializeError> {
+    let width = match marker {
+        Marker::Str8 | Marker::Bin8 | Marker::Ext8 => 1,
+        Marker::Str16 | Marker::Bin16 | Marker::Ext16 | Marker::Array16 | Marker::Map16 => 2,
+        Marker::Str32 | Marker::Bin32 | Marker::Ext32 | Marker::Array32 | Marker::Map32 => 4,
+        _ => 0,
+    };
+    w.write_u8(marker.into()).or(Err(SerializeError::FailedToWrite))?;
+    match width {
+        1 => w.write_u8(len as u8),
+        2 => w.write_u16::<BigEndian>(len as u16),
+        4 => w.write_u32::<BigEndian>(len as u32),
+        _ => Ok(()),
+    }.or(Err(SerializeError::FailedToWrite))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forged_container_lengths_fail() {
+        assert!(Marked::deserialize(&mut &[0xdd, 0xff, 0xff, 0xff, 0xff, 0xc0][..]).is_err());
+        assert!(Marked::deserialize(&mut &[0xdf, 0xff, 0xff, 0xff, 0xff, 0xc0][..]).is_err());
+    }
+
+    // {"a": [1, "x"], "m": {"k": -1}} with a non-minimal marker on every node but the keys.
+    // Offsets: the outer Map16 header is 0..3, "a" 3..5, its Array16 header 5..8, UInt32 1 8..13, Str8 "x" 13..16,
+    // "m" 16..18, the inner Map16 header 18..21, "k" 21..23 and Int16 -1 23..26.
+    const NESTED: [u8; 26] = [
+        0xde, 0x00, 0x02, 0xa1, b'a', 0xdc, 0x00, 0x02, 0xce, 0x00, 0x00, 0x00, 0x01, 0xd9, 0x01, b'x',
+        0xa1, b'm', 0xde, 0x00, 0x01, 0xa1, b'k', 0xd1, 0xff, 0xff,
+    ];
+
+    #[test]
+    fn nested_values_keep_their_markers() {
+        let marked = Marked::deserialize(&mut &NESTED[..]).unwrap();
+        assert_eq!(marked.marker, Some(Marker::Map16));
+        assert_eq!(marked.pointer("/a").unwrap().marker, Some(Marker::Array16));
+        assert_eq!(marked.pointer("/a/0").unwrap(), &Marked { marker: Some(Marker::UInt32), node: Node::UInt(1) });
+        assert_eq!(marked.pointer("/a/1").unwrap(), &Marked { marker: Some(Marker::Str8), node: Node::String(String::from("x")) });
+        assert_eq!(marked.pointer("/m").unwrap().marker, Some(Marker::Map16));
+        assert_eq!(marked.pointer("/m/k").unwrap(), &Marked { marker: Some(Marker::Int16), node: Node::Int(-1) });
+        assert_eq!(marked.serialize().unwrap(), NESTED);
+    }
+
+    #[test]
+    fn editing_a_nested_value_rewrites_only_its_bytes() {
+        let edit = |pointer: &str, node: Node| {
+            let mut marked = Marked::deserialize(&mut &NESTED[..]).unwrap();
+            marked.pointer_mut(pointer).unwrap().node = node;
+            marked.serialize().unwrap()
+        };
+
+        let w = edit("/a/0", Node::UInt(7));
+        assert_eq!((&w[..8], &w[8..13], &w[13..]), (&NESTED[..8], &[0xce, 0x00, 0x00, 0x00, 0x07][..], &NESTED[13..]));
+
+        let w = edit("/m/k", Node::Int(-2));
+        assert_eq!((&w[..23], &w[23..]), (&NESTED[..23], &[0xd1, 0xff, 0xfe][..]));
+
+        let w = edit("/a/1", Node::String(String::from("yz")));
+        assert_eq!((&w[..13], &w[13..17], &w[17..]), (&NESTED[..13], &[0xd9, 0x02, b'y', b'z'][..], &NESTED[16..]));
+    }
+}
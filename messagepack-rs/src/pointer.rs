@@ -0,0 +1,308 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use crate::deserializable::DeserializeError;
+use crate::marker::Marker;
+use crate::reader::{read_header_from, skip_value, Header};
+use crate::serializable::{Serializable, SerializeError};
+use std::ops::Range;
+
+/// Borrowed view of one encoded value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueRef<'a> {
+    Nil,
+    Bool(bool),
+    UInt(u64),
+    Int(i64),
+    Float32(f32),
+    Float64(f64),
+    Binary(&'a [u8]),
+    String(&'a str),
+    /// Encoded bytes of the whole array, which can be passed to `lookup` again.
+    Array(&'a [u8]),
+    /// Encoded bytes of the whole map, which can be passed to `lookup` again.
+    Map(&'a [u8]),
+    Extension(i8, &'a [u8]),
+}
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidPointer,
+    NotFound,
+    FailedToDeserialize(DeserializeError),
+    FailedToSerialize(SerializeError),
+}
+
+impl From<DeserializeError> for Error {
+    fn from(e: DeserializeError) -> Self {
+        Error::FailedToDeserialize(e)
+    }
+}
+
+impl From<SerializeError> for Error {
+    fn from(e: SerializeError) -> Self {
+        Error::FailedToSerialize(e)
+    }
+}
+
+/// Splits a JSON Pointer such as `/headers/trace_id` into unescaped reference tokens.
+pub(crate) fn parse(pointer: &str) -> Result<Vec<String>, Error> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(Error::InvalidPointer);
+    }
+    Ok(pointer[1..].split('/').map(|token| token.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+/// Parses an array index token, which RFC 6901 restricts to `0` or digits without a leading zero.
+pub(crate) fn parse_index(token: &str) -> Option<usize> {
+    match token.as_bytes() {
+        [b'0'] => Some(0),
+        [b'1'..=b'9', rest @ ..] if rest.iter().all(u8::is_ascii_digit) => token.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Offsets, relative to `buf`, of the child of the array or map at the start of `buf` named by `token`.
+/// The first offset is where the entry begins (its key, for maps) and the second where its value begins.
+pub(crate) fn find_child(buf: &[u8], token: &str) -> Result<Option<(usize, usize)>, DeserializeError> {
+    let (header, mut position) = read_header_from(buf)?;
+    match header {
+        Header::Array(n) => {
+            let index = match parse_index(token) {
+                Some(index) if index < n => index,
+                _ => return Ok(None),
+            };
+            for _ in 0..index {
+                position += skip_value(&buf[position..])?;
+            }
+            Ok(Some((position, position)))
+        },
+        Header::Map(n) => {
+            for _ in 0..n {
+                let (key_header, key_header_size) = read_header_from(&buf[position..])?;
+                if let Header::Str(size) = key_header {
+                    let key_start = position + key_header_size;
+                    let key = key_start.checked_add(size).and_then(|end| buf.get(key_start..end)).ok_or(DeserializeError::InvalidValue)?;
+                    if key == token.as_bytes() {
+                        return Ok(Some((position, key_start + size)));
+                    }
+                }
+                position += skip_value(&buf[position..])?;
+                position += skip_value(&buf[position..])?;
+            }
+            Ok(None)
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Returns the byte range of the value at `pointer` without decoding its siblings.
+///
+/// Map keys are matched against string keys only and array elements are addressed by index.
+/// `Ok(None)` is returned when the path does not exist.
+pub fn lookup_range(buf: &[u8], pointer: &str) -> Result<Option<Range<usize>>, Error> {
+    match walk(buf, &parse(pointer)?)? {
+        Some(start) => Ok(Some(start..start + skip_value(&buf[start..])?)),
+        None => Ok(None),
+    }
+}
+
+/// Offset of the value reached by following `tokens` from the start of `buf`.
+fn walk(buf: &[u8], tokens: &[String]) -> Result<Option<usize>, DeserializeError> {
+    let mut start = 0;
+    for token in tokens {
+        match find_child(&buf[start..], token)? {
+            Some((_, offset)) => start += offset,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(start))
+}
+
+/// Returns a borrowed view of the value at `pointer`. See `lookup_range`.
+pub fn lookup<'a>(buf: &'a [u8], pointer: &str) -> Result<Option<ValueRef<'a>>, Error> {
+    match lookup_range(buf, pointer)? {
+        Some(range) => Ok(Some(value_ref(&buf[range])?)),
+        None => Ok(None),
+    }
+}
+
+fn value_ref(raw: &[u8]) -> Result<ValueRef<'_>, DeserializeError> {
+    let (header, header_size) = read_header_from(raw)?;
+    let payload = &raw[header_size..];
+    Ok(match header {
+        Header::Nil => ValueRef::Nil,
+        Header::Bool(v) => ValueRef::Bool(v),
+        Header::UInt(v) => ValueRef::UInt(v),
+        Header::Int(v) => ValueRef::Int(v),
+        Header::Float32(v) => ValueRef::Float32(v),
+        Header::Float64(v) => ValueRef::Float64(v),
+        Header::Bin(_) => ValueRef::Binary(payload),
+        Header::Str(_) => ValueRef::String(std::str::from_utf8(payload).or(Err(DeserializeError::InvalidValue))?),
+        Header::Array(_) => ValueRef::Array(raw),
+        Header::Map(_) => ValueRef::Map(raw),
+        Header::Ext(t, _) => ValueRef::Extension(t, payload),
+    })
+}
+
+/// Returns a copy of `buf` with the value at `pointer` replaced by `value`, splicing only the affected region.
+///
+/// A missing map key is inserted at the end of its map and the token `-` appends to an array.
+/// In both cases the header of the enclosing container is rewritten with the new element count.
+pub fn set<S: Serializable>(buf: &[u8], pointer: &str, value: S) -> Result<Vec<u8>, Error> {
+    let tokens = parse(pointer)?;
+    let (last, parents) = match tokens.split_last() {
+        Some(split) => split,
+        None => return Ok(value.serialize()?),
+    };
+    let parent = walk(buf, parents)?.ok_or(Error::NotFound)?;
+    if let Some((_, offset)) = find_child(&buf[parent..], last)? {
+        let start = parent + offset;
+        let end = start + skip_value(&buf[start..])?;
+        return Ok([&buf[..start], &value.serialize()?, &buf[end..]].concat());
+    }
+
+    let (header, header_size) = read_header_from(&buf[parent..])?;
+    let (entry, new_header) = match header {
+        Header::Map(n) => ([S::serialize_string(last.clone())?, value.serialize()?].concat(), container_header(true, n + 1, header_size)?),
+        Header::Array(n) if last == "-" => (value.serialize()?, container_header(false, n + 1, header_size)?),
+        _ => return Err(Error::NotFound),
+    };
+    let end = parent + skip_value(&buf[parent..])?;
+    Ok([&buf[..parent], &new_header, &buf[parent + header_size..end], &entry, &buf[end..]].concat())
+}
+
+/// Returns a copy of `buf` without the map entry or array element at `pointer`, rewriting the header of its container.
+pub fn remove(buf: &[u8], pointer: &str) -> Result<Vec<u8>, Error> {
+    let tokens = parse(pointer)?;
+    let (last, parents) = tokens.split_last().ok_or(Error::InvalidPointer)?;
+    let parent = walk(buf, parents)?.ok_or(Error::NotFound)?;
+    let (entry, value) = find_child(&buf[parent..], last)?.ok_or(Error::NotFound)?;
+    let start = parent + entry;
+    let end = parent + value + skip_value(&buf[parent + value..])?;
+
+    let (header, header_size) = read_header_from(&buf[parent..])?;
+    let new_header = match header {
+        Header::Map(n) => container_header(true, n - 1, header_size)?,
+        Header::Array(n) => container_header(false, n - 1, header_size)?,
+        _ => return Err(Error::NotFound),
+    };
+    Ok([&buf[..parent], &new_header, &buf[parent + header_size..start], &buf[end..]].concat())
+}
+
+/// Encodes a map or array header for `len` elements, keeping the width of the
+/// `original_size`-byte header it replaces unless `len` no longer fits in it.
+fn container_header(map: bool, len: usize, original_size: usize) -> Result<Vec<u8>, SerializeError> {
+    let (fix, marker16, marker32) = if map {
+        (Marker::FixMap(len as u8), Marker::Map16, Marker::Map32)
+    } else {
+        (Marker::FixArray(len as u8), Marker::Array16, Marker::Array32)
+    };
+    let mut w = Vec::with_capacity(1 + 4);
+    if len <= 15 && original_size == 1 {
+        w.write_u8(fix.into()).or(Err(SerializeError::FailedToWrite))?;
+    } else if len <= u16::MAX as usize && original_size <= 1 + 2 {
+        w.write_u8(marker16.into()).or(Err(SerializeError::FailedToWrite))?;
+        w.write_u16::<BigEndian>(len as u16).or(Err(SerializeError::FailedToWrite))?;
+    } else if len <= u32::MAX as usize {
+        w.write_u8(marker32.into()).or(Err(SerializeError::FailedToWrite))?;
+        w.write_u32::<BigEndian>(len as u32).or(Err(SerializeError::FailedToWrite))?;
+    } else {
+        return Err(SerializeError::OutOfRange);
+    }
+    Ok(w)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_index_accepts_only_canonical_tokens() {
+        assert_eq!(parse_index("0"), Some(0));
+        assert_eq!(parse_index("10"), Some(10));
+        for token in &["", "00", "01", "+1", "-1", "-", "1a", " 1"] {
+            assert_eq!(parse_index(token), None, "{:?}", token);
+        }
+    }
+
+    #[test]
+    fn lookup_rejects_non_canonical_indices() {
+        let buf = [0x92, 0x0a, 0x0b];
+        assert_eq!(lookup_range(&buf, "/1").unwrap(), Some(2..3));
+        assert_eq!(lookup_range(&buf, "/01").unwrap(), None);
+        assert_eq!(lookup_range(&buf, "/+1").unwrap(), None);
+    }
+
+    /// A map or array header followed by `n` entries, where map entry `i` is the key `"a" + i` with the value `i`.
+    fn container(header: &[u8], map: bool, n: u8) -> Vec<u8> {
+        let mut buf = header.to_vec();
+        for i in 0..n {
+            if map {
+                buf.extend_from_slice(&[0xa1, b'a' + i]);
+            }
+            buf.push(i);
+        }
+        buf
+    }
+
+    #[test]
+    fn set_replaces_value_in_place() {
+        let buf = [0x81, 0xa1, b'a', 0x92, 0x01, 0x02];
+        assert_eq!(set(&buf, "/a/1", true).unwrap(), [0x81, 0xa1, b'a', 0x92, 0x01, 0xc3]);
+        assert_eq!(set(&buf, "", true).unwrap(), [0xc3]);
+    }
+
+    #[test]
+    fn set_inserts_map_key_and_widens_fixmap_to_map16() {
+        let buf = container(&[0x8e], true, 14);
+        assert_eq!(set(&buf, "/z", true).unwrap(), [&container(&[0x8f], true, 14)[..], &[0xa1, b'z', 0xc3]].concat());
+
+        let buf = container(&[0x8f], true, 15);
+        assert_eq!(set(&buf, "/z", true).unwrap(), [&container(&[0xde, 0x00, 0x10], true, 15)[..], &[0xa1, b'z', 0xc3]].concat());
+    }
+
+    #[test]
+    fn set_appends_to_array_with_dash() {
+        assert_eq!(set(&[0x92, 0x01, 0x02], "/-", true).unwrap(), [0x93, 0x01, 0x02, 0xc3]);
+
+        let buf = container(&[0x9f], false, 15);
+        assert_eq!(set(&buf, "/-", true).unwrap(), [&container(&[0xdc, 0x00, 0x10], false, 15)[..], &[0xc3]].concat());
+
+        // Only the last token may be `-`, and never on a map.
+        assert!(matches!(set(&[0x92, 0x01, 0x02], "/-/0", true), Err(Error::NotFound)));
+        assert_eq!(set(&[0x80], "/-", true).unwrap(), [0x81, 0xa1, b'-', 0xc3]);
+    }
+
+    #[test]
+    fn set_keeps_wide_headers() {
+        let buf = container(&[0xde, 0x00, 0x01], true, 1);
+        assert_eq!(set(&buf, "/b", true).unwrap(), [0xde, 0x00, 0x02, 0xa1, b'a', 0x00, 0xa1, b'b', 0xc3]);
+    }
+
+    #[test]
+    fn remove_map_entry() {
+        let buf = container(&[0x82], true, 2);
+        assert_eq!(remove(&buf, "/a").unwrap(), [0x81, 0xa1, b'b', 0x01]);
+        assert!(matches!(remove(&buf, "/c"), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn remove_from_map16_keeps_its_header_width() {
+        let buf = container(&[0xde, 0x00, 0x10], true, 16);
+        let expected = [&[0xde, 0x00, 0x0f][..], &container(&[], true, 16)[3..]].concat();
+        assert_eq!(remove(&buf, "/a").unwrap(), expected);
+
+        let buf = container(&[0xde, 0x00, 0x02], true, 2);
+        assert_eq!(remove(&buf, "/b").unwrap(), [0xde, 0x00, 0x01, 0xa1, b'a', 0x00]);
+    }
+
+    #[test]
+    fn remove_array_element() {
+        assert_eq!(remove(&[0x93, 0x01, 0x02, 0x03], "/1").unwrap(), [0x92, 0x01, 0x03]);
+        assert_eq!(remove(&[0xdc, 0x00, 0x01, 0x01], "/0").unwrap(), [0xdc, 0x00, 0x00]);
+        assert!(matches!(remove(&[0x91, 0x01], "/-"), Err(Error::NotFound)));
+        assert!(matches!(remove(&[0x91, 0x01], ""), Err(Error::InvalidPointer)));
+    }
+}
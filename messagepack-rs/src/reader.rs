@@ -0,0 +1,456 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use crate::decodable::Decodable;
+use crate::deserializable::DeserializeError;
+use crate::marker::Marker;
+use std::io::{self, Read};
+
+/// The head of one encoded value: its type plus either the scalar itself or the length of what follows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Header {
+    Nil,
+    Bool(bool),
+    UInt(u64),
+    Int(i64),
+    Float32(f32),
+    Float64(f64),
+    Str(usize),
+    Bin(usize),
+    Ext(i8, usize),
+    Array(usize),
+    Map(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExtMeta {
+    pub t: i8,
+    pub size: usize,
+}
+
+/// What a `Visitor` receives for a str whose payload is not valid UTF-8.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum InvalidUtf8 {
+    /// Fail with `InvalidValue`.
+    #[default]
+    Reject,
+    /// Pass the bytes to `visit_non_utf8_str`. A map key of `Value` cannot hold them and fails with `InvalidValue`.
+    Keep,
+    /// Pass the bytes to `visit_str` with invalid sequences replaced by U+FFFD.
+    Lossy,
+    /// Pass the bytes to `visit_bin`. A map key of `Value` cannot hold them and fails with `InvalidValue`.
+    Binary,
+}
+
+/// How a `Reader` treats input that is valid MessagePack but malformed for its type.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Options {
+    /// Hands a timestamp extension whose data is malformed to `Visitor::visit_ext` instead of failing.
+    pub lenient_timestamps: bool,
+    /// Hands a timestamp to `Visitor::visit_formatted_timestamp` along with the form it was encoded in.
+    pub preserve_timestamp_format: bool,
+    pub invalid_utf8: InvalidUtf8,
+    /// Hands every str to `Visitor::visit_bin`, for input in the legacy raw format where str carries any bytes.
+    pub str_as_binary: bool,
+}
+
+struct Counter<R: Read> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for Counter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// Pull reader that decodes MessagePack piece by piece without building `Value`s.
+pub struct Reader<R: Read> {
+    inner: Counter<R>,
+    options: Options,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_options(inner, Options::default())
+    }
+
+    pub fn with_options(inner: R, options: Options) -> Self {
+        Reader { inner: Counter { inner, count: 0 }, options }
+    }
+
+    pub fn options(&self) -> Options {
+        self.options
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner.inner
+    }
+
+    /// Number of bytes consumed since the reader was created.
+    pub fn position(&self) -> u64 {
+        self.inner.count
+    }
+
+    pub fn read_marker(&mut self) -> Result<Marker, DeserializeError> {
+        Ok(Marker::from(self.inner.read_u8().or(Err(DeserializeError::InvalidMarker))?))
+    }
+
+    pub fn read_header(&mut self) -> Result<Header, DeserializeError> {
+        self.read_header_with_marker().map(|(_, header)| header)
+    }
+
+    /// Reads a header along with the marker it was encoded with.
+    pub fn read_header_with_marker(&mut self) -> Result<(Marker, Header), DeserializeError> {
+        let r = &mut self.inner;
+        let marker = Marker::from(r.read_u8().or(Err(DeserializeError::InvalidMarker))?);
+        let header = match marker.clone() {
+            Marker::PositiveFixInt(n) => Header::UInt(u64::from(n)),
+            Marker::FixMap(n) => Header::Map(n as usize),
+            Marker::FixArray(n) => Header::Array(n as usize),
+            Marker::FixStr(n) => Header::Str(n as usize),
+            Marker::Nil => Header::Nil,
+            Marker::Reserved => Err(DeserializeError::InvalidMarker)?,
+            Marker::False => Header::Bool(false),
+            Marker::True => Header::Bool(true),
+            Marker::Bin8 => Header::Bin(r.read_u8().or(Err(DeserializeError::InvalidLength))? as usize),
+            Marker::Bin16 => Header::Bin(r.read_u16::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize),
+            Marker::Bin32 => Header::Bin(r.read_u32::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize),
+            Marker::Ext8 => {
+                let size = r.read_u8().or(Err(DeserializeError::InvalidLength))? as usize;
+                Header::Ext(r.read_i8().or(Err(DeserializeError::InvalidLength))?, size)
+            },
+            Marker::Ext16 => {
+                let size = r.read_u16::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize;
+                Header::Ext(r.read_i8().or(Err(DeserializeError::InvalidLength))?, size)
+            },
+            Marker::Ext32 => {
+                let size = r.read_u32::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize;
+                Header::Ext(r.read_i8().or(Err(DeserializeError::InvalidLength))?, size)
+            },
+            Marker::Float32 => Header::Float32(r.read_f32::<BigEndian>().or(Err(DeserializeError::InvalidValue))?),
+            Marker::Float64 => Header::Float64(r.read_f64::<BigEndian>().or(Err(DeserializeError::InvalidValue))?),
+            Marker::UInt8 => Header::UInt(u64::from(r.read_u8().or(Err(DeserializeError::InvalidValue))?)),
+            Marker::UInt16 => Header::UInt(u64::from(r.read_u16::<BigEndian>().or(Err(DeserializeError::InvalidValue))?)),
+            Marker::UInt32 => Header::UInt(u64::from(r.read_u32::<BigEndian>().or(Err(DeserializeError::InvalidValue))?)),
+            Marker::UInt64 => Header::UInt(r.read_u64::<BigEndian>().or(Err(DeserializeError::InvalidValue))?),
+            Marker::Int8 => Header::Int(i64::from(r.read_i8().or(Err(DeserializeError::InvalidValue))?)),
+            Marker::Int16 => Header::Int(i64::from(r.read_i16::<BigEndian>().or(Err(DeserializeError::InvalidValue))?)),
+            Marker::Int32 => Header::Int(i64::from(r.read_i32::<BigEndian>().or(Err(DeserializeError::InvalidValue))?)),
+            Marker::Int64 => Header::Int(r.read_i64::<BigEndian>().or(Err(DeserializeError::InvalidValue))?),
+            Marker::FixExt1 => Header::Ext(r.read_i8().or(Err(DeserializeError::InvalidLength))?, 1),
+            Marker::FixExt2 => Header::Ext(r.read_i8().or(Err(DeserializeError::InvalidLength))?, 2),
+            Marker::FixExt4 => Header::Ext(r.read_i8().or(Err(DeserializeError::InvalidLength))?, 4),
+            Marker::FixExt8 => Header::Ext(r.read_i8().or(Err(DeserializeError::InvalidLength))?, 8),
+            Marker::FixExt16 => Header::Ext(r.read_i8().or(Err(DeserializeError::InvalidLength))?, 16),
+            Marker::Str8 => Header::Str(r.read_u8().or(Err(DeserializeError::InvalidLength))? as usize),
+            Marker::Str16 => Header::Str(r.read_u16::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize),
+            Marker::Str32 => Header::Str(r.read_u32::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize),
+            Marker::Array16 => Header::Array(r.read_u16::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize),
+            Marker::Array32 => Header::Array(r.read_u32::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize),
+            Marker::Map16 => Header::Map(r.read_u16::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize),
+            Marker::Map32 => Header::Map(r.read_u32::<BigEndian>().or(Err(DeserializeError::InvalidLength))? as usize),
+            Marker::NegativeFixInt(n) => Header::Int(i64::from(n)),
+        };
+        Ok((marker, header))
+    }
+
+    pub fn read_nil(&mut self) -> Result<(), DeserializeError> {
+        match self.read_header()? {
+            Header::Nil => Ok(()),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, DeserializeError> {
+        match self.read_header()? {
+            Header::Bool(v) => Ok(v),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+
+    /// Like every integer reader, accepts any integer marker and fails with `OutOfRange` when the value does not fit.
+    pub fn read_u8(&mut self) -> Result<u8, DeserializeError> {
+        self.decode()
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, DeserializeError> {
+        self.decode()
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, DeserializeError> {
+        self.decode()
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, DeserializeError> {
+        self.decode()
+    }
+
+    pub fn read_i8(&mut self) -> Result<i8, DeserializeError> {
+        self.decode()
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16, DeserializeError> {
+        self.decode()
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, DeserializeError> {
+        self.decode()
+    }
+
+    pub fn read_i64(&mut self) -> Result<i64, DeserializeError> {
+        self.decode()
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, DeserializeError> {
+        match self.read_header()? {
+            Header::Float32(v) => Ok(v),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, DeserializeError> {
+        match self.read_header()? {
+            Header::Float32(v) => Ok(f64::from(v)),
+            Header::Float64(v) => Ok(v),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+
+    pub fn read_array_len(&mut self) -> Result<usize, DeserializeError> {
+        match self.read_header()? {
+            Header::Array(n) => Ok(n),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+
+    pub fn read_map_len(&mut self) -> Result<usize, DeserializeError> {
+        match self.read_header()? {
+            Header::Map(n) => Ok(n),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+
+    pub fn read_str_len(&mut self) -> Result<usize, DeserializeError> {
+        match self.read_header()? {
+            Header::Str(n) => Ok(n),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+
+    /// Reads a string into `buf`, reusing its allocation.
+    pub fn read_str<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<&'b str, DeserializeError> {
+        let size = self.read_str_len()?;
+        self.read_payload(size, buf)?;
+        std::str::from_utf8(buf).or(Err(DeserializeError::InvalidValue))
+    }
+
+    pub fn read_bin_len(&mut self) -> Result<usize, DeserializeError> {
+        match self.read_header()? {
+            Header::Bin(n) => Ok(n),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+
+    /// Reads a binary into `buf`, reusing its allocation.
+    pub fn read_bin<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<&'b [u8], DeserializeError> {
+        let size = self.read_bin_len()?;
+        self.read_payload(size, buf)?;
+        Ok(buf)
+    }
+
+    /// Reads the type and data size of an extension. The `size` bytes of data follow.
+    pub fn read_ext_meta(&mut self) -> Result<ExtMeta, DeserializeError> {
+        match self.read_header()? {
+            Header::Ext(t, size) => Ok(ExtMeta { t, size }),
+            _ => Err(DeserializeError::InvalidMarker),
+        }
+    }
+
+    /// Decodes one value into `T`. See `Decodable`.
+    pub fn decode<T: Decodable>(&mut self) -> Result<T, DeserializeError> {
+        T::decode(self)
+    }
+
+    /// Advances past one complete value of any depth using only its length headers and returns the number of bytes it spanned.
+    pub fn skip_value(&mut self) -> Result<u64, DeserializeError> {
+        let start = self.position();
+        let mut remaining: usize = 1;
+        while remaining > 0 {
+            let header = self.read_header()?;
+            remaining = pending_values(remaining, header)?;
+            if let Header::Str(size) | Header::Bin(size) | Header::Ext(_, size) = header {
+                let skipped = io::copy(&mut self.inner.by_ref().take(size as u64), &mut io::sink()).or(Err(DeserializeError::InvalidValue))?;
+                if skipped != size as u64 {
+                    return Err(DeserializeError::InvalidValue);
+                }
+            }
+        }
+        Ok(self.position() - start)
+    }
+
+    /// Reads a `size`-byte payload into `buf`, growing it as bytes arrive so that a forged size cannot force a huge allocation.
+    pub(crate) fn read_payload(&mut self, size: usize, buf: &mut Vec<u8>) -> Result<(), DeserializeError> {
+        buf.clear();
+        let read = self.inner.by_ref().take(size as u64).read_to_end(buf).or(Err(DeserializeError::InvalidValue))?;
+        if read != size {
+            return Err(DeserializeError::InvalidValue);
+        }
+        Ok(())
+    }
+}
+
+/// Upper bound on the elements preallocated for a container, whose length comes from untrusted input.
+pub(crate) const MAX_PREALLOCATED: usize = 4096;
+
+/// Number of values still to be read after consuming `header`, counting the elements it opens.
+fn pending_values(remaining: usize, header: Header) -> Result<usize, DeserializeError> {
+    match header {
+        Header::Array(n) => remaining.checked_add(n),
+        Header::Map(n) => n.checked_mul(2).and_then(|n| remaining.checked_add(n)),
+        _ => Some(remaining),
+    }.map(|remaining| remaining - 1).ok_or(DeserializeError::InvalidLength)
+}
+
+pub(crate) fn read_header_from(buf: &[u8]) -> Result<(Header, usize), DeserializeError> {
+    let mut reader = Reader::new(buf);
+    let header = reader.read_header()?;
+    Ok((header, reader.position() as usize))
+}
+
+/// Returns the number of bytes spanned by the complete value at the start of `buf`, without decoding it.
+pub fn skip_value(buf: &[u8]) -> Result<usize, DeserializeError> {
+    let mut position = 0;
+    let mut remaining: usize = 1;
+    while remaining > 0 {
+        let (header, header_size) = read_header_from(&buf[position..])?;
+        position += header_size;
+        remaining = pending_values(remaining, header)?;
+        if let Header::Str(size) | Header::Bin(size) | Header::Ext(_, size) = header {
+            position = position.checked_add(size).filter(|&end| end <= buf.len()).ok_or(DeserializeError::InvalidValue)?;
+        }
+    }
+    Ok(position)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Token<'a> {
+    Nil,
+    Bool(bool),
+    UInt(u64),
+    Int(i64),
+    Float32(f32),
+    Float64(f64),
+    Str(&'a str),
+    Bin(&'a [u8]),
+    Ext(i8, &'a [u8]),
+    ArrayStart(usize),
+    MapStart(usize),
+}
+
+/// Iterates over the tokens of an encoded buffer, borrowing strings and binaries from it.
+///
+/// Arrays and maps are flattened: `ArrayStart(n)` is followed by the tokens of its `n` elements and
+/// `MapStart(n)` by the tokens of its `n` key/value pairs. Iteration stops after the first error.
+pub struct Tokenizer<'a> {
+    buf: &'a [u8],
+    position: usize,
+    failed: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Tokenizer { buf, position: 0, failed: false }
+    }
+
+    /// Offset of the next token in the buffer.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Advances past the next complete value, including all tokens of an array or map, and returns the number of bytes it spanned.
+    pub fn skip_value(&mut self) -> Result<usize, DeserializeError> {
+        let size = skip_value(&self.buf[self.position..])?;
+        self.position += size;
+        Ok(size)
+    }
+
+    fn next_token(&mut self) -> Result<Token<'a>, DeserializeError> {
+        let (header, header_size) = read_header_from(&self.buf[self.position..])?;
+        let start = self.position + header_size;
+        let token = match header {
+            Header::Nil => Token::Nil,
+            Header::Bool(v) => Token::Bool(v),
+            Header::UInt(v) => Token::UInt(v),
+            Header::Int(v) => Token::Int(v),
+            Header::Float32(v) => Token::Float32(v),
+            Header::Float64(v) => Token::Float64(v),
+            Header::Array(n) => Token::ArrayStart(n),
+            Header::Map(n) => Token::MapStart(n),
+            Header::Str(size) => {
+                let payload = self.payload(start, size)?;
+                self.position = start + size;
+                return Ok(Token::Str(std::str::from_utf8(payload).or(Err(DeserializeError::InvalidValue))?));
+            },
+            Header::Bin(size) => {
+                let payload = self.payload(start, size)?;
+                self.position = start + size;
+                return Ok(Token::Bin(payload));
+            },
+            Header::Ext(t, size) => {
+                let payload = self.payload(start, size)?;
+                self.position = start + size;
+                return Ok(Token::Ext(t, payload));
+            },
+        };
+        self.position = start;
+        Ok(token)
+    }
+
+    fn payload(&self, start: usize, size: usize) -> Result<&'a [u8], DeserializeError> {
+        start.checked_add(size).and_then(|end| self.buf.get(start..end)).ok_or(DeserializeError::InvalidValue)
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token<'a>, DeserializeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.position >= self.buf.len() {
+            return None;
+        }
+        let result = self.next_token();
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forged_payload_size_fails() {
+        let mut buf = Vec::new();
+        assert!(matches!(Reader::new(&[0xc6, 0xff, 0xff, 0xff, 0xff, 0x01][..]).read_bin(&mut buf), Err(DeserializeError::InvalidValue)));
+        assert!(matches!(Reader::new(&[0xdb, 0xff, 0xff, 0xff, 0xff, 0x61][..]).read_str(&mut buf), Err(DeserializeError::InvalidValue)));
+    }
+
+    #[test]
+    fn read_payload_reuses_the_buffer() {
+        let mut reader = Reader::new(&[0xc4, 0x02, 0x01, 0x02, 0xc4, 0x01, 0x03][..]);
+        let mut buf = Vec::new();
+        assert_eq!(reader.read_bin(&mut buf).unwrap(), [0x01, 0x02]);
+        assert_eq!(reader.read_bin(&mut buf).unwrap(), [0x03]);
+        assert_eq!(reader.position(), 7);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::deserializable::DeserializeError;
+use crate::marked::write_header;
+use crate::reader::{Header, Reader};
+use crate::serializable::SerializeError;
+use crate::timestamp::Timestamp;
+use std::io::{BufRead, Write};
+
+#[derive(Debug)]
+pub enum Error {
+    FailedToFillBuf,
+    FailedToDeserialize(DeserializeError),
+    FailedToSerialize(SerializeError),
+}
+
+impl From<DeserializeError> for Error {
+    fn from(e: DeserializeError) -> Self {
+        Error::FailedToDeserialize(e)
+    }
+}
+
+impl From<SerializeError> for Error {
+    fn from(e: SerializeError) -> Self {
+        Error::FailedToSerialize(e)
+    }
+}
+
+/// Sizes of the input and output of a repack.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Repacked {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+impl Repacked {
+    pub fn bytes_saved(&self) -> u64 {
+        self.bytes_read.saturating_sub(self.bytes_written)
+    }
+}
+
+/// Rewrites every value of `buf_reader` into `writer` with the smallest markers, header by header, without building values.
+///
+/// Integers, strings, binaries, arrays, maps and extensions get their shortest header, using FixExt when the data length allows it,
+/// and well-formed timestamps are re-encoded in their shortest form. Signed integers that are not negative become unsigned.
+/// Floats and payloads are copied unchanged.
+/// The input must end on a value boundary, otherwise it fails with `InvalidLength`.
+pub fn repack<R: BufRead, W: Write>(buf_reader: R, writer: &mut W) -> Result<Repacked, Error> {
+    let mut reader = Reader::new(buf_reader);
+    let mut bytes_written = 0;
+    let mut pending: usize = 0;
+    let mut w = Vec::new();
+    let mut payload = Vec::new();
+    while !reader.get_mut().fill_buf().or(Err(Error::FailedToFillBuf))?.is_empty() {
+        let header = reader.read_header()?;
+        // Elements still expected by the open containers, where this value takes one unless it is at the top level.
+        pending = match header {
+            Header::Array(n) => pending.saturating_sub(1).checked_add(n),
+            Header::Map(n) => n.checked_mul(2).and_then(|n| pending.saturating_sub(1).checked_add(n)),
+            _ => Some(pending.saturating_sub(1)),
+        }.ok_or(DeserializeError::InvalidLength)?;
+
+        w.clear();
+        match header {
+            Header::Str(size) | Header::Bin(size) => {
+                reader.read_payload(size, &mut payload)?;
+                write_header(None, header, &mut w)?;
+                w.extend_from_slice(&payload);
+            },
+            Header::Ext(t, size) => {
+                reader.read_payload(size, &mut payload)?;
+                if t == Timestamp::EXTENSION_TYPE {
+                    if let Ok(timestamp) = Timestamp::from_extension_data(&payload) {
+                        payload = timestamp.to_extension_data(timestamp.format())?;
+                    }
+                }
+                write_header(None, Header::Ext(t, payload.len()), &mut w)?;
+                w.extend_from_slice(&payload);
+            },
+            header => write_header(None, header, &mut w)?,
+        }
+        writer.write_all(&w).or(Err(SerializeError::FailedToWrite))?;
+        bytes_written += w.len() as u64;
+    }
+    if pending > 0 {
+        return Err(DeserializeError::InvalidLength.into());
+    }
+    Ok(Repacked { bytes_read: reader.position(), bytes_written })
+}
+
+/// Repacks the values in `buf` into a new buffer. See `repack`.
+pub fn repack_slice(buf: &[u8]) -> Result<(Vec<u8>, Repacked), Error> {
+    let mut w = Vec::with_capacity(buf.len());
+    let repacked = repack(buf, &mut w)?;
+    Ok((w, repacked))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repacked(buf: &[u8]) -> Vec<u8> {
+        let (w, repacked) = repack_slice(buf).unwrap();
+        assert_eq!(repacked, Repacked { bytes_read: buf.len() as u64, bytes_written: w.len() as u64 });
+        w
+    }
+
+    #[test]
+    fn minimizes_scalars() {
+        assert_eq!(repacked(&[0xd1, 0x00, 0x05, 0xd1, 0xff, 0xfe]), [0x05, 0xfe]);
+        assert_eq!(repacked(&[0xcf, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00]), [0xcd, 0x01, 0x00]);
+        assert_eq!(repacked(&[0xd9, 0x02, b'i', b'd', 0xc5, 0x00, 0x01, 0xff]), [0xa2, b'i', b'd', 0xc4, 0x01, 0xff]);
+        assert_eq!(repacked(&[0xcb, 0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]), [0xcb, 0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn minimizes_extensions_and_timestamps() {
+        assert_eq!(repacked(&[0xc7, 0x04, 0x05, 0x01, 0x02, 0x03, 0x04]), [0xd6, 0x05, 0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(repacked(&[0xd7, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]), [0xd6, 0xff, 0x00, 0x00, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn counts_nested_container_elements() {
+        let buf = [0xdc, 0x00, 0x02, 0x91, 0xd0, 0x05, 0xde, 0x00, 0x01, 0xd9, 0x01, b'k', 0x90];
+        assert_eq!(repacked(&buf), [0x92, 0x91, 0x05, 0x81, 0xa1, b'k', 0x90]);
+    }
+
+    #[test]
+    fn repacks_every_top_level_value() {
+        assert_eq!(repacked(&[0xd0, 0x01, 0xdc, 0x00, 0x00, 0x91, 0x91, 0xc0, 0xc0]), [0x01, 0x90, 0x91, 0x91, 0xc0, 0xc0]);
+        assert_eq!(repacked(&[]), []);
+    }
+
+    #[test]
+    fn rejects_truncated_containers() {
+        for buf in &[&[0x92, 0x01][..], &[0x81, 0xa1, b'k'], &[0x91, 0x92, 0x01], &[0xdc, 0x00, 0x01]] {
+            assert!(matches!(repack_slice(buf), Err(Error::FailedToDeserialize(DeserializeError::InvalidLength))), "{:x?}", buf);
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use byteorder::{BigEndian, WriteBytesExt};
-use chrono::prelude::*;
 use crate::binary::Binary;
 use crate::extension::Extension;
 use crate::marker::Marker;
+use crate::timestamp::{Timestamp, TimestampFormat};
 use std::collections::BTreeMap;
 use std::io::Write;
 
@@ -10,6 +10,46 @@ use std::io::Write;
 pub enum SerializeError {
     FailedToWrite,
     OutOfRange,
+    /// The value has no encoding in the selected format, such as an extension in the legacy raw format.
+    Unsupported,
+}
+
+mod std_types;
+
+/// Encodes the header of an array of `len` elements in its smallest form.
+pub fn serialize_array_header(len: usize) -> Result<Vec<u8>, SerializeError> {
+    match len {
+        len if len <= 15 => Ok(vec![Marker::FixArray(len as u8).into()]),
+        len if u16::MAX as usize >= len => {
+            let mut w = vec![Marker::Array16.into()];
+            w.write_u16::<BigEndian>(len as u16).or(Err(SerializeError::FailedToWrite))?;
+            Ok(w)
+        },
+        len if u32::MAX as usize >= len => {
+            let mut w = vec![Marker::Array32.into()];
+            w.write_u32::<BigEndian>(len as u32).or(Err(SerializeError::FailedToWrite))?;
+            Ok(w)
+        },
+        _ => Err(SerializeError::OutOfRange),
+    }
+}
+
+/// Encodes the header of a map of `len` entries in its smallest form.
+pub fn serialize_map_header(len: usize) -> Result<Vec<u8>, SerializeError> {
+    match len {
+        len if len <= 15 => Ok(vec![Marker::FixMap(len as u8).into()]),
+        len if u16::MAX as usize >= len => {
+            let mut w = vec![Marker::Map16.into()];
+            w.write_u16::<BigEndian>(len as u16).or(Err(SerializeError::FailedToWrite))?;
+            Ok(w)
+        },
+        len if u32::MAX as usize >= len => {
+            let mut w = vec![Marker::Map32.into()];
+            w.write_u32::<BigEndian>(len as u32).or(Err(SerializeError::FailedToWrite))?;
+            Ok(w)
+        },
+        _ => Err(SerializeError::OutOfRange),
+    }
 }
 
 pub trait Serializable: Sized {
@@ -55,7 +95,7 @@ pub trait Serializable: Sized {
             let mut w = Vec::with_capacity(1);
             w.write_u8(v as u8).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v <= u16::from(u8::max_value()) {
+        } else if v <= u16::from(u8::MAX) {
             let mut w = Vec::with_capacity(1 + 1);
             w.write_u8(Marker::UInt8.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_u8(v as u8).or(Err(SerializeError::FailedToWrite))?;
@@ -73,12 +113,12 @@ pub trait Serializable: Sized {
             let mut w = Vec::with_capacity(1);
             w.write_u8(v as u8).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v <= u32::from(u8::max_value()) {
+        } else if v <= u32::from(u8::MAX) {
             let mut w = Vec::with_capacity(1 + 1);
             w.write_u8(Marker::UInt8.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_u8(v as u8).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v <= u32::from(u16::max_value()) {
+        } else if v <= u32::from(u16::MAX) {
             let mut w = Vec::with_capacity(1 + 2);
             w.write_u8(Marker::UInt16.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_u16::<BigEndian>(v as u16).or(Err(SerializeError::FailedToWrite))?;
@@ -96,17 +136,17 @@ pub trait Serializable: Sized {
             let mut w = Vec::with_capacity(1);
             w.write_u8(v as u8).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v <= u64::from(u8::max_value()) {
+        } else if v <= u64::from(u8::MAX) {
             let mut w = Vec::with_capacity(1 + 1);
             w.write_u8(Marker::UInt8.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_u8(v as u8).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v <= u64::from(u16::max_value()) {
+        } else if v <= u64::from(u16::MAX) {
             let mut w = Vec::with_capacity(1 + 2);
             w.write_u8(Marker::UInt16.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_u16::<BigEndian>(v as u16).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v <= u64::from(u32::max_value()) {
+        } else if v <= u64::from(u32::MAX) {
             let mut w = Vec::with_capacity(1 + 4);
             w.write_u8(Marker::UInt32.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_u32::<BigEndian>(v as u32).or(Err(SerializeError::FailedToWrite))?;
@@ -137,7 +177,7 @@ pub trait Serializable: Sized {
             let mut w = Vec::with_capacity(1);
             w.write_i8(v as i8).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v >= i16::from(i8::min_value()) && v <= i16::from(i8::max_value()) {
+        } else if v >= i16::from(i8::MIN) && v <= i16::from(i8::MAX) {
             let mut w = Vec::with_capacity(1 + 1);
             w.write_u8(Marker::Int8.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_i8(v as i8).or(Err(SerializeError::FailedToWrite))?;
@@ -155,12 +195,12 @@ pub trait Serializable: Sized {
             let mut w = Vec::with_capacity(1);
             w.write_i8(v as i8).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v >= i32::from(i8::min_value()) && v <= i32::from(i8::max_value()) {
+        } else if v >= i32::from(i8::MIN) && v <= i32::from(i8::MAX) {
             let mut w = Vec::with_capacity(1 + 1);
             w.write_u8(Marker::Int8.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_i8(v as i8).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v >= i32::from(i16::min_value()) && v <= i32::from(i16::max_value()) {
+        } else if v >= i32::from(i16::MIN) && v <= i32::from(i16::MAX) {
             let mut w = Vec::with_capacity(1 + 2);
             w.write_u8(Marker::Int16.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_i16::<BigEndian>(v as i16).or(Err(SerializeError::FailedToWrite))?;
@@ -178,17 +218,17 @@ pub trait Serializable: Sized {
             let mut w = Vec::with_capacity(1);
             w.write_i8(v as i8).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v >= i64::from(i8::min_value()) && v <= i64::from(i8::max_value()) {
+        } else if v >= i64::from(i8::MIN) && v <= i64::from(i8::MAX) {
             let mut w = Vec::with_capacity(1 + 1);
             w.write_u8(Marker::Int8.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_i8(v as i8).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v >= i64::from(i16::min_value()) && v <= i64::from(i16::max_value()) {
+        } else if v >= i64::from(i16::MIN) && v <= i64::from(i16::MAX) {
             let mut w = Vec::with_capacity(1 + 2);
             w.write_u8(Marker::Int16.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_i16::<BigEndian>(v as i16).or(Err(SerializeError::FailedToWrite))?;
             Ok(w)
-        } else if v >= i64::from(i32::min_value()) && v <= i64::from(i32::max_value()) {
+        } else if v >= i64::from(i32::MIN) && v <= i64::from(i32::MAX) {
             let mut w = Vec::with_capacity(1 + 4);
             w.write_u8(Marker::Int32.into()).or(Err(SerializeError::FailedToWrite))?;
             w.write_i32::<BigEndian>(v as i32).or(Err(SerializeError::FailedToWrite))?;
@@ -203,19 +243,19 @@ pub trait Serializable: Sized {
 
     fn serialize_binary(v: Binary) -> Result<Vec<u8>, SerializeError> {
         let mut w = match v.0.len() {
-            len if u8::max_value() as usize >= len => {
+            len if u8::MAX as usize >= len => {
                 let mut w = Vec::with_capacity(1 + 1 + len);
                 w.write_u8(Marker::Bin8.into()).or(Err(SerializeError::FailedToWrite))?;
                 w.write_u8(len as u8).or(Err(SerializeError::FailedToWrite))?;
                 w
             },
-            len if u16::max_value() as usize >= len => {
+            len if u16::MAX as usize >= len => {
                 let mut w = Vec::with_capacity(1 + 2 + len);
                 w.write_u8(Marker::Bin16.into()).or(Err(SerializeError::FailedToWrite))?;
                 w.write_u16::<BigEndian>(len as u16).or(Err(SerializeError::FailedToWrite))?;
                 w
             },
-            len if u32::max_value() as usize >= len => {
+            len if u32::MAX as usize >= len => {
                 let mut w = Vec::with_capacity(1 + 4 + len);
                 w.write_u8(Marker::Bin32.into()).or(Err(SerializeError::FailedToWrite))?;
                 w.write_u32::<BigEndian>(len as u32).or(Err(SerializeError::FailedToWrite))?;
@@ -228,25 +268,30 @@ pub trait Serializable: Sized {
     }
 
     fn serialize_string(v: String) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_string_bytes(v.into_bytes())
+    }
+
+    /// Serializes `v` as a str without checking that it is valid UTF-8.
+    fn serialize_string_bytes(v: Vec<u8>) -> Result<Vec<u8>, SerializeError> {
         let mut w = match v.len() {
             len if len <= 31 => {
                 let mut w = Vec::with_capacity(1 + len);
                 w.write_u8(Marker::FixStr(len as u8).into()).or(Err(SerializeError::FailedToWrite))?;
                 w
             },
-            len if u8::max_value() as usize >= len => {
+            len if u8::MAX as usize >= len => {
                 let mut w = Vec::with_capacity(1 + 1 + len);
                 w.write_u8(Marker::Str8.into()).or(Err(SerializeError::FailedToWrite))?;
                 w.write_u8(len as u8).or(Err(SerializeError::FailedToWrite))?;
                 w
             },
-            len if u16::max_value() as usize >= len => {
+            len if u16::MAX as usize >= len => {
                 let mut w = Vec::with_capacity(1 + 2 + len);
                 w.write_u8(Marker::Str16.into()).or(Err(SerializeError::FailedToWrite))?;
                 w.write_u16::<BigEndian>(len as u16).or(Err(SerializeError::FailedToWrite))?;
                 w
             },
-            len if u32::max_value() as usize >= len => {
+            len if u32::MAX as usize >= len => {
                 let mut w = Vec::with_capacity(1 + 4 + len);
                 w.write_u8(Marker::Str32.into()).or(Err(SerializeError::FailedToWrite))?;
                 w.write_u32::<BigEndian>(len as u32).or(Err(SerializeError::FailedToWrite))?;
@@ -254,57 +299,20 @@ pub trait Serializable: Sized {
             },
             _ => Err(SerializeError::OutOfRange)?,
         };
-        w.write_all(v.as_bytes()).or(Err(SerializeError::FailedToWrite))?;
+        w.write_all(&v).or(Err(SerializeError::FailedToWrite))?;
         Ok(w)
     }
 
     fn serialize_array(v: Vec<Self>) -> Result<Vec<u8>, SerializeError> {
-        let mut values = Vec::new();
-        let len = v.len();
+        let mut w = serialize_array_header(v.len())?;
         for vv in v.into_iter() {
-            values.append(&mut Self::serialize(vv)?);
+            w.append(&mut vv.serialize()?);
         }
-        let mut w = match len {
-            len if len <= 15 => {
-                let mut w = Vec::with_capacity(1 + values.len());
-                w.write_u8(Marker::FixArray(len as u8).into()).or(Err(SerializeError::FailedToWrite))?;
-                w
-            },
-            len if u16::max_value() as usize >= len => {
-                let mut w = Vec::with_capacity(1 + 2 + values.len());
-                w.write_u8(Marker::Array16.into()).or(Err(SerializeError::FailedToWrite))?;
-                w.write_u16::<BigEndian>(len as u16).or(Err(SerializeError::FailedToWrite))?;
-                w
-            },
-            len if u32::max_value() as usize >= len => {
-                let mut w = Vec::with_capacity(1 + 4 + values.len());
-                w.write_u8(Marker::Array32.into()).or(Err(SerializeError::FailedToWrite))?;
-                w.write_u32::<BigEndian>(len as u32).or(Err(SerializeError::FailedToWrite))?;
-                w
-            },
-            _ => Err(SerializeError::OutOfRange)?,
-        };
-        w.append(&mut values);
         Ok(w)
     }
 
     fn serialize_map(v: BTreeMap<String, Self>) -> Result<Vec<u8>, SerializeError> {
-        let mut w = match v.len() {
-            len if len <= 15 => {
-                vec![Marker::FixMap(len as u8).into()]
-            },
-            len if u16::max_value() as usize >= len => {
-                let mut w = vec![Marker::Map16.into()];
-                w.write_u16::<BigEndian>(len as u16).or(Err(SerializeError::FailedToWrite))?;
-                w
-            },
-            len if u32::max_value() as usize >= len => {
-                let mut w = vec![Marker::Map32.into()];
-                w.write_u32::<BigEndian>(len as u32).or(Err(SerializeError::FailedToWrite))?;
-                w
-            },
-            _ => Err(SerializeError::OutOfRange)?,
-        };
+        let mut w = serialize_map_header(v.len())?;
         for (k, v) in v {
             w.write_all(&Self::serialize_string(k)?).or(Err(SerializeError::FailedToWrite))?;
             w.write_all(&v.serialize()?).or(Err(SerializeError::FailedToWrite))?;
@@ -339,19 +347,19 @@ pub trait Serializable: Sized {
                 w.write_u8(Marker::FixExt16.into()).or(Err(SerializeError::FailedToWrite))?;
                 w
             },
-            len if len <= u8::max_value() as usize => {
+            len if len <= u8::MAX as usize => {
                 let mut w = Vec::with_capacity(1 + 1 + 1 + len);
                 w.write_u8(Marker::Ext8.into()).or(Err(SerializeError::FailedToWrite))?;
                 w.write_u8(len as u8).or(Err(SerializeError::FailedToWrite))?;
                 w
             },
-            len if len <= u16::max_value() as usize => {
+            len if len <= u16::MAX as usize => {
                 let mut w = Vec::with_capacity(1 + 2 + 1 + len);
                 w.write_u8(Marker::Ext16.into()).or(Err(SerializeError::FailedToWrite))?;
                 w.write_u16::<BigEndian>(len as u16).or(Err(SerializeError::FailedToWrite))?;
                 w
             },
-            len if len <= u32::max_value() as usize => {
+            len if len <= u32::MAX as usize => {
                 let mut w = Vec::with_capacity(1 + 4 + 1 + len);
                 w.write_u8(Marker::Ext32.into()).or(Err(SerializeError::FailedToWrite))?;
                 w.write_u32::<BigEndian>(len as u32).or(Err(SerializeError::FailedToWrite))?;
@@ -364,30 +372,21 @@ pub trait Serializable: Sized {
         Ok(w)
     }
 
-    fn serialize_timestamp(v: DateTime<Utc>) -> Result<Vec<u8>, SerializeError> {
-        if v.timestamp() >> 34 == 0 {
-            let value = (u64::from(v.timestamp_subsec_nanos()) << 34) | (v.timestamp() as u64);
-            if value & 0xff_ff_ff_ff_00_00_00_00 == 0 {
-                let mut w = Vec::with_capacity(1 + 1 + 4);
-                w.write_u8(Marker::FixExt4.into()).or(Err(SerializeError::FailedToWrite))?;
-                w.write_i8(-1).or(Err(SerializeError::FailedToWrite))?;
-                w.write_u32::<BigEndian>(value as u32).or(Err(SerializeError::FailedToWrite))?;
-                Ok(w)
-            } else {
-                let mut w = Vec::with_capacity(1 + 1 + 8);
-                w.write_u8(Marker::FixExt8.into()).or(Err(SerializeError::FailedToWrite))?;
-                w.write_i8(-1).or(Err(SerializeError::FailedToWrite))?;
-                w.write_u64::<BigEndian>(value).or(Err(SerializeError::FailedToWrite))?;
-                Ok(w)
-            }
-        } else {
-            let mut w = Vec::with_capacity(1 + 1 + 1 + 4 + 8);
-            w.write_u8(Marker::Ext8.into()).or(Err(SerializeError::FailedToWrite))?;
-            w.write_u8(12).or(Err(SerializeError::FailedToWrite))?;
-            w.write_i8(-1).or(Err(SerializeError::FailedToWrite))?;
-            w.write_u32::<BigEndian>(v.timestamp_subsec_nanos() as u32).or(Err(SerializeError::FailedToWrite))?;
-            w.write_i64::<BigEndian>(v.timestamp()).or(Err(SerializeError::FailedToWrite))?;
-            Ok(w)
-        }
+    fn serialize_timestamp(v: Timestamp) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_timestamp_in(v, v.format())
+    }
+
+    /// Serializes `v` in the given form of the timestamp extension, failing with `OutOfRange` when it does not fit that form.
+    fn serialize_timestamp_in(v: Timestamp, format: TimestampFormat) -> Result<Vec<u8>, SerializeError> {
+        let data = v.to_extension_data(format)?;
+        let mut w = Vec::with_capacity(1 + 1 + 1 + data.len());
+        match format {
+            TimestampFormat::Timestamp32 => w.write_u8(Marker::FixExt4.into()),
+            TimestampFormat::Timestamp64 => w.write_u8(Marker::FixExt8.into()),
+            TimestampFormat::Timestamp96 => w.write_u8(Marker::Ext8.into()).and_then(|_| w.write_u8(12)),
+        }.or(Err(SerializeError::FailedToWrite))?;
+        w.write_i8(Timestamp::EXTENSION_TYPE).or(Err(SerializeError::FailedToWrite))?;
+        w.extend_from_slice(&data);
+        Ok(w)
     }
 }
@@ -0,0 +1,212 @@
+use crate::binary::Binary;
+use crate::extension::Extension;
+use crate::timestamp::Timestamp;
+use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
+use super::{serialize_array_header, serialize_map_header, Serializable, SerializeError};
+
+impl Serializable for bool {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_bool(self)
+    }
+}
+
+impl Serializable for f32 {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_float32(self)
+    }
+}
+
+impl Serializable for f64 {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_float64(self)
+    }
+}
+
+impl Serializable for u8 {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_uint8(self)
+    }
+}
+
+impl Serializable for u16 {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_uint16(self)
+    }
+}
+
+impl Serializable for u32 {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_uint32(self)
+    }
+}
+
+impl Serializable for u64 {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_uint64(self)
+    }
+}
+
+impl Serializable for usize {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_uint64(self as u64)
+    }
+}
+
+impl Serializable for i8 {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_int8(self)
+    }
+}
+
+impl Serializable for i16 {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_int16(self)
+    }
+}
+
+impl Serializable for i32 {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_int32(self)
+    }
+}
+
+impl Serializable for i64 {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_int64(self)
+    }
+}
+
+impl Serializable for isize {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_int64(self as i64)
+    }
+}
+
+impl Serializable for String {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_string(self)
+    }
+}
+
+impl Serializable for &str {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_string(String::from(self))
+    }
+}
+
+/// The way to write bytes as a bin, since `Vec<u8>` and `&[u8]` are written as arrays.
+impl Serializable for Binary {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_binary(self)
+    }
+}
+
+impl Serializable for Extension {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_extension(self)
+    }
+}
+
+impl Serializable for Timestamp {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_timestamp(self)
+    }
+}
+
+impl Serializable for SystemTime {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_timestamp(Timestamp::from(self))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Serializable for chrono::DateTime<chrono::Utc> {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_timestamp(Timestamp::from(self))
+    }
+}
+
+#[cfg(feature = "time")]
+impl Serializable for time::OffsetDateTime {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        Self::serialize_timestamp(Timestamp::from(self))
+    }
+}
+
+/// `None` is written as nil.
+impl<T: Serializable> Serializable for Option<T> {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        match self {
+            Some(v) => v.serialize(),
+            None => Self::serialize_nil(),
+        }
+    }
+}
+
+/// Every vector is an array, so a `Vec<u8>` is written as an array of integers, as with `Value::from([u8; N])`.
+/// Wrap bytes in `Binary` to write a bin.
+impl<T: Serializable> Serializable for Vec<T> {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        let mut w = serialize_array_header(self.len())?;
+        for v in self {
+            w.append(&mut v.serialize()?);
+        }
+        Ok(w)
+    }
+}
+
+impl<T: Serializable + Clone> Serializable for &[T] {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        let mut w = serialize_array_header(self.len())?;
+        for v in self {
+            w.append(&mut v.clone().serialize()?);
+        }
+        Ok(w)
+    }
+}
+
+impl<K: Serializable, V: Serializable> Serializable for BTreeMap<K, V> {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        let mut w = serialize_map_header(self.len())?;
+        for (k, v) in self {
+            w.append(&mut k.serialize()?);
+            w.append(&mut v.serialize()?);
+        }
+        Ok(w)
+    }
+}
+
+impl<K: Serializable, V: Serializable, S> Serializable for HashMap<K, V, S> {
+    fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+        let mut w = serialize_map_header(self.len())?;
+        for (k, v) in self {
+            w.append(&mut k.serialize()?);
+            w.append(&mut v.serialize()?);
+        }
+        Ok(w)
+    }
+}
+
+macro_rules! impl_serializable_for_tuple {
+    ($len:expr, $($t:ident $v:ident),+) => {
+        /// Tuples are written as arrays.
+        impl<$($t: Serializable),+> Serializable for ($($t,)+) {
+            fn serialize(self) -> Result<Vec<u8>, SerializeError> {
+                let ($($v,)+) = self;
+                let mut w = serialize_array_header($len)?;
+                $(w.append(&mut $v.serialize()?);)+
+                Ok(w)
+            }
+        }
+    };
+}
+
+impl_serializable_for_tuple!(1, A a);
+impl_serializable_for_tuple!(2, A a, B b);
+impl_serializable_for_tuple!(3, A a, B b, C c);
+impl_serializable_for_tuple!(4, A a, B b, C c, D d);
+impl_serializable_for_tuple!(5, A a, B b, C c, D d, E e);
+impl_serializable_for_tuple!(6, A a, B b, C c, D d, E e, F f);
+impl_serializable_for_tuple!(7, A a, B b, C c, D d, E e, F f, G g);
+impl_serializable_for_tuple!(8, A a, B b, C c, D d, E e, F f, G g, H h);
@@ -1,2 +1,3 @@
 pub mod deserializer;
+pub mod raw_deserializer;
 pub mod serializer;
@@ -1,5 +1,5 @@
 use crate::deserializable::Deserializable;
-use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::io::{BufRead, BufReader, Read, Seek};
 use std::marker::PhantomData;
 
 pub struct Deserializer<D: Deserializable, R: Read + Seek> {
@@ -27,7 +27,7 @@ impl<D: Deserializable, R: Read + Seek> Iterator for Deserializer<D, R> {
         match self.buf_reader.fill_buf() {
             Ok(result) => {
                 if !result.is_empty() {
-                    match self.buf_reader.seek(SeekFrom::Current(0)) {
+                    match self.buf_reader.stream_position() {
                         Ok(position) => {
                             match D::deserialize(&mut self.buf_reader) {
                                 Ok(v) => Some(Ok((v, position))),
@@ -0,0 +1,98 @@
+use crate::reader::{self, Reader};
+use super::deserializer::Error;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Copies every byte read from `inner` into `record`.
+struct Recorder<'a, R: Read> {
+    inner: &'a mut R,
+    record: Vec<u8>,
+}
+
+impl<'a, R: Read> Read for Recorder<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.record.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Yields each top-level record as its encoded bytes together with its offset from where reading started.
+///
+/// Records are only validated structurally, so they can be relayed, hashed or indexed without being decoded.
+/// Iteration stops after the first malformed record, since the records after it cannot be located.
+pub struct RawDeserializer<R: Read> {
+    buf_reader: BufReader<R>,
+    position: u64,
+    failed: bool,
+}
+
+impl<R: Read> RawDeserializer<R> {
+    pub fn new(buf_reader: BufReader<R>) -> Self {
+        RawDeserializer { buf_reader, position: 0, failed: false }
+    }
+}
+
+impl<R: Read> Iterator for RawDeserializer<R> {
+    type Item = Result<(Vec<u8>, u64), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.buf_reader.fill_buf() {
+            Ok(result) => {
+                if !result.is_empty() {
+                    let position = self.position;
+                    let mut reader = Reader::new(Recorder { inner: &mut self.buf_reader, record: Vec::new() });
+                    let result = reader.skip_value();
+                    let recorder = reader.into_inner();
+                    self.position += recorder.record.len() as u64;
+                    match result {
+                        Ok(_) => Some(Ok((recorder.record, position))),
+                        _ => {
+                            self.failed = true;
+                            Some(Err(Error::FailedToDeserialize(position)))
+                        },
+                    }
+                } else {
+                    None
+                }
+            },
+            _ => Some(Err(Error::FailedToFillBuf)),
+        }
+    }
+}
+
+/// Like `RawDeserializer`, but borrows each record from an in-memory buffer. Iteration stops after the first error.
+pub struct RawSliceDeserializer<'a> {
+    buf: &'a [u8],
+    position: usize,
+    failed: bool,
+}
+
+impl<'a> RawSliceDeserializer<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        RawSliceDeserializer { buf, position: 0, failed: false }
+    }
+}
+
+impl<'a> Iterator for RawSliceDeserializer<'a> {
+    type Item = Result<(&'a [u8], usize), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.position >= self.buf.len() {
+            return None;
+        }
+        let position = self.position;
+        match reader::skip_value(&self.buf[position..]) {
+            Ok(size) => {
+                self.position += size;
+                Some(Ok((&self.buf[position..self.position], position)))
+            },
+            _ => {
+                self.failed = true;
+                Some(Err(Error::FailedToDeserialize(position as u64)))
+            },
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use crate::legacy;
 use crate::serializable::*;
+use crate::value::Value;
 use std::io::{BufWriter, Error, Write};
 
 pub struct Serializer<T: Write>(BufWriter<T>);
@@ -12,6 +14,11 @@ impl<W: Write> Serializer<W> {
         self.0.write(&value.serialize()?).or(Err(SerializeError::FailedToWrite))
     }
 
+    /// Writes `value` in the legacy raw format. See `legacy::serialize`.
+    pub fn serialize_legacy<T: Into<Value>>(&mut self, value: T) -> Result<usize, SerializeError> {
+        self.0.write(&legacy::serialize(value)?).or(Err(SerializeError::FailedToWrite))
+    }
+
     pub fn get_ref(&self) -> &W {
         self.0.get_ref()
     }
@@ -0,0 +1,199 @@
+use byteorder::{BigEndian, ByteOrder};
+use crate::deserializable::DeserializeError;
+use crate::serializable::SerializeError;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A point in time as seconds and nanoseconds since the Unix epoch.
+///
+/// It covers the full range of the timestamp extension, so every timestamp decodes without loss.
+/// Conversions to `SystemTime`, `chrono::DateTime<Utc>` (feature `chrono`) and `time::OffsetDateTime` (feature `time`)
+/// fail with `OutOfRange` when the target cannot represent the value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub seconds: i64,
+    pub nanos: u32,
+}
+
+/// The 32, 64 and 96-bit forms of the timestamp extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TimestampFormat {
+    /// Seconds in `0..2^32` without nanoseconds.
+    Timestamp32,
+    /// Seconds in `0..2^34` with nanoseconds.
+    Timestamp64,
+    /// Any seconds with nanoseconds.
+    Timestamp96,
+}
+
+impl TimestampFormat {
+    pub(crate) fn from_data_len(len: usize) -> Option<Self> {
+        match len {
+            4 => Some(TimestampFormat::Timestamp32),
+            8 => Some(TimestampFormat::Timestamp64),
+            12 => Some(TimestampFormat::Timestamp96),
+            _ => None,
+        }
+    }
+}
+
+impl Timestamp {
+    pub const EXTENSION_TYPE: i8 = -1;
+
+    pub fn new(seconds: i64, nanos: u32) -> Self {
+        Timestamp { seconds, nanos }
+    }
+
+    pub fn now() -> Self {
+        Self::from(SystemTime::now())
+    }
+
+    /// The smallest form that holds this timestamp, which `serialize_timestamp` uses.
+    pub fn format(&self) -> TimestampFormat {
+        if self.seconds >> 34 != 0 {
+            TimestampFormat::Timestamp96
+        } else if self.nanos != 0 || self.seconds >> 32 != 0 {
+            TimestampFormat::Timestamp64
+        } else {
+            TimestampFormat::Timestamp32
+        }
+    }
+
+    /// Encodes the data of a timestamp extension in `format`.
+    ///
+    /// Fails with `OutOfRange` when the nanoseconds exceed 999,999,999 or the timestamp does not fit `format`.
+    pub fn to_extension_data(&self, format: TimestampFormat) -> Result<Vec<u8>, SerializeError> {
+        if self.nanos > 999_999_999 {
+            return Err(SerializeError::OutOfRange);
+        }
+        match format {
+            TimestampFormat::Timestamp32 if self.nanos == 0 && self.seconds >> 32 == 0 => Ok((self.seconds as u32).to_be_bytes().to_vec()),
+            TimestampFormat::Timestamp64 if self.seconds >> 34 == 0 => Ok(((u64::from(self.nanos) << 34) | self.seconds as u64).to_be_bytes().to_vec()),
+            TimestampFormat::Timestamp96 => Ok([&self.nanos.to_be_bytes()[..], &self.seconds.to_be_bytes()[..]].concat()),
+            _ => Err(SerializeError::OutOfRange),
+        }
+    }
+
+    /// Decodes the data of a timestamp extension in its 32, 64 or 96-bit form.
+    ///
+    /// Any other length fails with `InvalidTimestampLength` and nanoseconds above 999,999,999 fail with `InvalidTimestampNanoseconds`.
+    pub fn from_extension_data(data: &[u8]) -> Result<Self, DeserializeError> {
+        let timestamp = match data.len() {
+            4 => Timestamp { seconds: i64::from(BigEndian::read_u32(data)), nanos: 0 },
+            8 => {
+                let value = BigEndian::read_u64(data);
+                Timestamp { seconds: (value & 0x00_00_00_03_ff_ff_ff_ff) as i64, nanos: (value >> 34) as u32 }
+            },
+            12 => Timestamp { seconds: BigEndian::read_i64(&data[4..]), nanos: BigEndian::read_u32(&data[..4]) },
+            _ => return Err(DeserializeError::InvalidTimestampLength),
+        };
+        if timestamp.nanos > 999_999_999 {
+            return Err(DeserializeError::InvalidTimestampNanoseconds);
+        }
+        Ok(timestamp)
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(value: SystemTime) -> Self {
+        match value.duration_since(UNIX_EPOCH) {
+            Ok(d) => Timestamp { seconds: d.as_secs() as i64, nanos: d.subsec_nanos() },
+            Err(e) => {
+                let d = e.duration();
+                if d.subsec_nanos() == 0 {
+                    Timestamp { seconds: -(d.as_secs() as i64), nanos: 0 }
+                } else {
+                    Timestamp { seconds: -(d.as_secs() as i64) - 1, nanos: 1_000_000_000 - d.subsec_nanos() }
+                }
+            },
+        }
+    }
+}
+
+impl TryFrom<Timestamp> for SystemTime {
+    type Error = DeserializeError;
+
+    fn try_from(value: Timestamp) -> Result<Self, Self::Error> {
+        let seconds = Duration::from_secs(value.seconds.unsigned_abs());
+        if value.seconds >= 0 { UNIX_EPOCH.checked_add(seconds) } else { UNIX_EPOCH.checked_sub(seconds) }
+            .and_then(|v| v.checked_add(Duration::from_nanos(u64::from(value.nanos))))
+            .ok_or(DeserializeError::OutOfRange)
+    }
+}
+
+/// A leap second is clamped to the last nanosecond of the second before it.
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+    fn from(value: chrono::DateTime<chrono::Utc>) -> Self {
+        Timestamp { seconds: value.timestamp(), nanos: value.timestamp_subsec_nanos().min(999_999_999) }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Timestamp> for chrono::DateTime<chrono::Utc> {
+    type Error = DeserializeError;
+
+    fn try_from(value: Timestamp) -> Result<Self, Self::Error> {
+        use chrono::TimeZone;
+        chrono::Utc.timestamp_opt(value.seconds, value.nanos).single().ok_or(DeserializeError::OutOfRange)
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Timestamp {
+    fn from(value: time::OffsetDateTime) -> Self {
+        Timestamp { seconds: value.unix_timestamp(), nanos: value.nanosecond() }
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<Timestamp> for time::OffsetDateTime {
+    type Error = DeserializeError;
+
+    fn try_from(value: Timestamp) -> Result<Self, Self::Error> {
+        time::OffsetDateTime::from_unix_timestamp(value.seconds).ok()
+            .and_then(|v| v.checked_add(time::Duration::nanoseconds(i64::from(value.nanos))))
+            .ok_or(DeserializeError::OutOfRange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the smallest form of `timestamp` and its encoding, then decodes it back and round-trips it through `SystemTime`.
+    fn round_trip(timestamp: Timestamp, format: TimestampFormat, data: &[u8]) {
+        assert_eq!(timestamp.format(), format);
+        assert_eq!(timestamp.to_extension_data(format).unwrap(), data);
+        assert_eq!(Timestamp::from_extension_data(data).unwrap(), timestamp);
+        assert_eq!(Timestamp::from(SystemTime::try_from(timestamp).unwrap()), timestamp);
+    }
+
+    #[test]
+    fn round_trips_at_format_boundaries() {
+        round_trip(Timestamp::new(0, 0), TimestampFormat::Timestamp32, &[0x00, 0x00, 0x00, 0x00]);
+        round_trip(Timestamp::new((1 << 32) - 1, 0), TimestampFormat::Timestamp32, &[0xff, 0xff, 0xff, 0xff]);
+        round_trip(Timestamp::new(1 << 32, 0), TimestampFormat::Timestamp64, &[0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00]);
+        round_trip(Timestamp::new((1 << 34) - 1, 0), TimestampFormat::Timestamp64, &[0x00, 0x00, 0x00, 0x03, 0xff, 0xff, 0xff, 0xff]);
+        round_trip(Timestamp::new(1 << 34, 0), TimestampFormat::Timestamp96, &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00]);
+        round_trip(Timestamp::new(0, 999_999_999), TimestampFormat::Timestamp64, &[0xee, 0x6b, 0x27, 0xfc, 0x00, 0x00, 0x00, 0x00]);
+        round_trip(Timestamp::new(-1, 999_999_999), TimestampFormat::Timestamp96, &[0x3b, 0x9a, 0xc9, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn pre_epoch_system_times_borrow_from_the_seconds() {
+        assert_eq!(Timestamp::from(UNIX_EPOCH - Duration::from_nanos(1)), Timestamp::new(-1, 999_999_999));
+        assert_eq!(Timestamp::from(UNIX_EPOCH - Duration::from_millis(1_500)), Timestamp::new(-2, 500_000_000));
+        assert_eq!(Timestamp::from(UNIX_EPOCH - Duration::from_secs(2)), Timestamp::new(-2, 0));
+        assert_eq!(SystemTime::try_from(Timestamp::new(-2, 500_000_000)).unwrap(), UNIX_EPOCH - Duration::from_millis(1_500));
+    }
+
+    #[test]
+    fn rejects_out_of_range_nanoseconds_and_forms() {
+        assert!(matches!(Timestamp::new(0, 1_000_000_000).to_extension_data(TimestampFormat::Timestamp96), Err(SerializeError::OutOfRange)));
+        assert!(matches!(Timestamp::new(1 << 32, 0).to_extension_data(TimestampFormat::Timestamp32), Err(SerializeError::OutOfRange)));
+        assert!(matches!(Timestamp::new(-1, 0).to_extension_data(TimestampFormat::Timestamp64), Err(SerializeError::OutOfRange)));
+        assert!(matches!(Timestamp::from_extension_data(&[0xee, 0x6b, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00]), Err(DeserializeError::InvalidTimestampNanoseconds)));
+        assert!(matches!(Timestamp::from_extension_data(&[0x00; 6]), Err(DeserializeError::InvalidTimestampLength)));
+    }
+}
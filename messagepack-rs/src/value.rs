@@ -1,10 +1,23 @@
 use crate::binary::Binary;
-use crate::deserializable::Deserializable;
+use crate::deserializable::{Deserializable, DeserializeError};
 use crate::extension::Extension;
+use crate::extension::registry::TypedExtension;
+use crate::reader::{Options, Reader};
 use crate::serializable::{Serializable, SerializeError};
-use chrono::prelude::*;
+use crate::timestamp::{Timestamp, TimestampFormat};
+use crate::visitor::Visitor;
 use messagepack_rs_macros::MessagePackFrom;
+use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::io::Read;
+use std::iter::FromIterator;
+use std::time::SystemTime;
+
+mod ordering;
+mod semantic;
+
+pub use self::ordering::HashableValue;
+pub use self::semantic::FloatEq;
 
 #[derive(Clone, Debug, PartialEq, MessagePackFrom)]
 pub enum Value {
@@ -25,7 +38,116 @@ pub enum Value {
     Array(Vec<Self>),
     Map(BTreeMap<String, Self>),
     Extension(Extension),
-    Timestamp(DateTime<Utc>),
+    Timestamp(Timestamp),
+    TypedExtension(TypedExtension),
+    /// A timestamp with the form it was decoded from, which it is serialized in again.
+    /// Produced instead of `Timestamp` when reading with `preserve_timestamp_format`.
+    FormattedTimestamp(Timestamp, TimestampFormat),
+    /// A str whose payload is not valid UTF-8, kept as its bytes and serialized as a str again.
+    /// Produced when reading with `InvalidUtf8::Keep`.
+    NonUtf8String(Vec<u8>),
+}
+
+impl From<TypedExtension> for Value {
+    fn from(value: TypedExtension) -> Self {
+        Self::TypedExtension(value)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Self::UInt64(value as u64)
+    }
+}
+
+impl From<isize> for Value {
+    fn from(value: isize) -> Self {
+        Self::Int64(value as i64)
+    }
+}
+
+impl From<char> for Value {
+    fn from(value: char) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<Cow<'_, str>> for Value {
+    fn from(value: Cow<'_, str>) -> Self {
+        Self::String(value.into_owned())
+    }
+}
+
+impl From<Box<str>> for Value {
+    fn from(value: Box<str>) -> Self {
+        Self::String(String::from(value))
+    }
+}
+
+/// Borrowed bytes become a `Binary`. A `Vec<u8>` converts element by element into an `Array`,
+/// like any other `Vec`, so owned bytes go through `Value::from(Binary::from(bytes))` instead.
+impl From<&[u8]> for Value {
+    fn from(value: &[u8]) -> Self {
+        Self::Binary(Binary(value.to_vec()))
+    }
+}
+
+impl From<SystemTime> for Value {
+    fn from(value: SystemTime) -> Self {
+        Self::Timestamp(Timestamp::from(value))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Value {
+    fn from(value: chrono::DateTime<chrono::Utc>) -> Self {
+        Self::Timestamp(Timestamp::from(value))
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Value {
+    fn from(value: time::OffsetDateTime) -> Self {
+        Self::Timestamp(Timestamp::from(value))
+    }
+}
+
+/// Arrays of any element type, bytes included, become an `Array`, as a `Vec` does.
+impl<T: Into<Value>, const N: usize> From<[T; N]> for Value {
+    fn from(value: [T; N]) -> Self {
+        Self::Array(IntoIterator::into_iter(value).map(Into::into).collect())
+    }
+}
+
+macro_rules! impl_from_tuple {
+    ($($t:ident $v:ident),+) => {
+        impl<$($t: Into<Value>),+> From<($($t,)+)> for Value {
+            fn from(($($v,)+): ($($t,)+)) -> Self {
+                Self::Array(vec![$($v.into()),+])
+            }
+        }
+    };
+}
+
+impl_from_tuple!(A a);
+impl_from_tuple!(A a, B b);
+impl_from_tuple!(A a, B b, C c);
+impl_from_tuple!(A a, B b, C c, D d);
+impl_from_tuple!(A a, B b, C c, D d, E e);
+impl_from_tuple!(A a, B b, C c, D d, E e, F f);
+impl_from_tuple!(A a, B b, C c, D d, E e, F f, G g);
+impl_from_tuple!(A a, B b, C c, D d, E e, F f, G g, H h);
+
+impl FromIterator<Value> for Value {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        Self::Array(iter.into_iter().collect())
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::Map(iter.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
 }
 
 impl Serializable for Value {
@@ -49,8 +171,37 @@ impl Serializable for Value {
             Self::Map(v) => Self::serialize_map(v),
             Self::Extension(v) => Self::serialize_extension(v),
             Self::Timestamp(v) => Self::serialize_timestamp(v),
+            Self::FormattedTimestamp(v, format) => Self::serialize_timestamp_in(v, format),
+            Self::NonUtf8String(v) => Self::serialize_string_bytes(v),
+            Self::TypedExtension(v) => Self::serialize_extension(v.to_extension()),
         }
     }
 }
 
 impl Deserializable for Value {}
+
+impl Value {
+    /// Deserializes one value through `Visitor`, applying `options` to malformed timestamps and strings.
+    pub fn deserialize_with_options<R: Read>(buf_reader: &mut R, options: Options) -> Result<Self, DeserializeError> {
+        Self::visit(&mut Reader::with_options(buf_reader, options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors_convert_element_by_element() {
+        assert_eq!(Value::from(vec![1u32, 2]), Value::Array(vec![Value::UInt32(1), Value::UInt32(2)]));
+        assert_eq!(Value::from(vec![1u8, 2]), Value::Array(vec![Value::UInt8(1), Value::UInt8(2)]));
+        assert_eq!(Value::from(vec![Value::Nil]), Value::Array(vec![Value::Nil]));
+        assert_eq!(Value::from(&[Value::Nil][..]), Value::Array(vec![Value::Nil]));
+    }
+
+    #[test]
+    fn bytes_convert_to_binary() {
+        assert_eq!(Value::from(&[1u8, 2][..]), Value::Binary(Binary(vec![1, 2])));
+        assert_eq!(Value::from(Binary::from(vec![1u8, 2])), Value::Binary(Binary(vec![1, 2])));
+    }
+}
@@ -0,0 +1,224 @@
+use super::Value;
+use crate::timestamp::TimestampFormat;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+/// A `Value` with total `Eq`, `Ord` and `Hash`, so it can be a `HashMap` key, a `HashSet` element or sorted.
+///
+/// Values compare as `Value::total_cmp` does, so two values are equal only when they have the same variant:
+/// normalize them first to treat integers of different widths as equal.
+#[derive(Clone, Debug)]
+pub struct HashableValue(pub Value);
+
+impl From<Value> for HashableValue {
+    fn from(value: Value) -> Self {
+        HashableValue(value)
+    }
+}
+
+impl From<HashableValue> for Value {
+    fn from(value: HashableValue) -> Self {
+        value.0
+    }
+}
+
+impl PartialEq for HashableValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.total_cmp(&other.0) == Ordering::Equal
+    }
+}
+
+impl Eq for HashableValue {}
+
+impl PartialOrd for HashableValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HashableValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Hash for HashableValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_value(&self.0, state);
+    }
+}
+
+impl Value {
+    /// A total order over all values, usable with `sort_by`.
+    ///
+    /// Values of different variants are ordered by their declaration order in `Value`.
+    /// Floats are ordered by `f64::total_cmp`, which puts negative NaNs first, positive NaNs last and `-0.0` before `0.0`.
+    /// Arrays and maps are ordered lexicographically, and typed extensions by their type and encoded data.
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Float32(a), Value::Float32(b)) => a.total_cmp(b),
+            (Value::Float64(a), Value::Float64(b)) => a.total_cmp(b),
+            (Value::UInt8(a), Value::UInt8(b)) => a.cmp(b),
+            (Value::UInt16(a), Value::UInt16(b)) => a.cmp(b),
+            (Value::UInt32(a), Value::UInt32(b)) => a.cmp(b),
+            (Value::UInt64(a), Value::UInt64(b)) => a.cmp(b),
+            (Value::Int8(a), Value::Int8(b)) => a.cmp(b),
+            (Value::Int16(a), Value::Int16(b)) => a.cmp(b),
+            (Value::Int32(a), Value::Int32(b)) => a.cmp(b),
+            (Value::Int64(a), Value::Int64(b)) => a.cmp(b),
+            (Value::Binary(a), Value::Binary(b)) => a.0.cmp(&b.0),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Array(a), Value::Array(b)) => a.iter().zip(b).map(|(a, b)| a.total_cmp(b)).find(|o| o.is_ne()).unwrap_or_else(|| a.len().cmp(&b.len())),
+            (Value::Map(a), Value::Map(b)) => a.iter().zip(b).map(|((ka, va), (kb, vb))| ka.cmp(kb).then_with(|| va.total_cmp(vb))).find(|o| o.is_ne()).unwrap_or_else(|| a.len().cmp(&b.len())),
+            (Value::Extension(a), Value::Extension(b)) => (a.t, &a.data).cmp(&(b.t, &b.data)),
+            (Value::Timestamp(a), Value::Timestamp(b)) => a.cmp(b),
+            (Value::TypedExtension(a), Value::TypedExtension(b)) => {
+                let (a, b) = (a.to_extension(), b.to_extension());
+                (a.t, a.data).cmp(&(b.t, b.data))
+            },
+            (Value::FormattedTimestamp(a, fa), Value::FormattedTimestamp(b, fb)) => a.cmp(b).then_with(|| format_rank(*fa).cmp(&format_rank(*fb))),
+            (Value::NonUtf8String(a), Value::NonUtf8String(b)) => a.cmp(b),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+/// Position of the variant of `v` in the declaration of `Value`.
+fn rank(v: &Value) -> u8 {
+    match v {
+        Value::Nil => 0,
+        Value::Bool(_) => 1,
+        Value::Float32(_) => 2,
+        Value::Float64(_) => 3,
+        Value::UInt8(_) => 4,
+        Value::UInt16(_) => 5,
+        Value::UInt32(_) => 6,
+        Value::UInt64(_) => 7,
+        Value::Int8(_) => 8,
+        Value::Int16(_) => 9,
+        Value::Int32(_) => 10,
+        Value::Int64(_) => 11,
+        Value::Binary(_) => 12,
+        Value::String(_) => 13,
+        Value::Array(_) => 14,
+        Value::Map(_) => 15,
+        Value::Extension(_) => 16,
+        Value::Timestamp(_) => 17,
+        Value::TypedExtension(_) => 18,
+        Value::FormattedTimestamp(_, _) => 19,
+        Value::NonUtf8String(_) => 20,
+    }
+}
+
+fn format_rank(format: TimestampFormat) -> u8 {
+    match format {
+        TimestampFormat::Timestamp32 => 0,
+        TimestampFormat::Timestamp64 => 1,
+        TimestampFormat::Timestamp96 => 2,
+    }
+}
+
+/// Hashes exactly what `total_cmp` compares, so equal values hash equally.
+fn hash_value<H: Hasher>(v: &Value, state: &mut H) {
+    rank(v).hash(state);
+    match v {
+        Value::Nil => (),
+        Value::Bool(v) => v.hash(state),
+        Value::Float32(v) => v.to_bits().hash(state),
+        Value::Float64(v) => v.to_bits().hash(state),
+        Value::UInt8(v) => v.hash(state),
+        Value::UInt16(v) => v.hash(state),
+        Value::UInt32(v) => v.hash(state),
+        Value::UInt64(v) => v.hash(state),
+        Value::Int8(v) => v.hash(state),
+        Value::Int16(v) => v.hash(state),
+        Value::Int32(v) => v.hash(state),
+        Value::Int64(v) => v.hash(state),
+        Value::Binary(v) => v.0.hash(state),
+        Value::String(v) => v.hash(state),
+        Value::Array(v) => {
+            v.len().hash(state);
+            v.iter().for_each(|v| hash_value(v, state));
+        },
+        Value::Map(v) => {
+            v.len().hash(state);
+            v.iter().for_each(|(k, v)| {
+                k.hash(state);
+                hash_value(v, state);
+            });
+        },
+        Value::Extension(v) => (v.t, &v.data).hash(state),
+        Value::Timestamp(v) => v.hash(state),
+        Value::TypedExtension(v) => {
+            let v = v.to_extension();
+            (v.t, v.data).hash(state);
+        },
+        Value::FormattedTimestamp(v, format) => (v, format_rank(*format)).hash(state),
+        Value::NonUtf8String(v) => v.hash(state),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+
+    fn hash(value: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        HashableValue(value.clone()).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn samples() -> Vec<Value> {
+        vec![
+            Value::Float64(f64::NAN),
+            Value::Float64(f64::from_bits(f64::NAN.to_bits() | 1)),
+            Value::Float64(-f64::NAN),
+            Value::Float64(0.0),
+            Value::Float64(-0.0),
+            Value::Float32(0.0),
+            Value::UInt8(1),
+            Value::UInt16(1),
+            Value::Int8(1),
+            Value::Int64(1),
+            Value::Array(vec![Value::UInt8(1)]),
+            Value::Array(vec![Value::Int64(1)]),
+        ]
+    }
+
+    #[test]
+    fn equal_values_hash_equally() {
+        for a in samples() {
+            for b in samples() {
+                let equal = HashableValue(a.clone()) == HashableValue(b.clone());
+                assert_eq!(equal, a.total_cmp(&b) == Ordering::Equal, "{:?} {:?}", a, b);
+                if equal {
+                    assert_eq!(hash(&a), hash(&b), "{:?} {:?}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nans_and_zeros_are_told_apart_by_bits() {
+        assert_eq!(HashableValue(Value::Float64(f64::NAN)), HashableValue(Value::Float64(f64::NAN)));
+        assert_ne!(HashableValue(Value::Float64(f64::NAN)), HashableValue(Value::Float64(-f64::NAN)));
+        assert_ne!(HashableValue(Value::Float64(0.0)), HashableValue(Value::Float64(-0.0)));
+        assert_eq!(Value::Float64(-0.0).total_cmp(&Value::Float64(0.0)), Ordering::Less);
+        assert_eq!(Value::Float64(-f64::NAN).total_cmp(&Value::Float64(f64::NEG_INFINITY)), Ordering::Less);
+
+        let set: HashSet<_> = vec![f64::NAN, f64::NAN, 0.0, -0.0, 0.0].into_iter().map(|v| HashableValue(Value::Float64(v))).collect();
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn integer_variants_differ_until_normalized() {
+        let values = vec![Value::UInt8(1), Value::UInt16(1), Value::Int8(1), Value::Int64(1)];
+        let set: HashSet<_> = values.iter().cloned().map(HashableValue).collect();
+        assert_eq!(set.len(), 4);
+        let set: HashSet<_> = values.into_iter().map(|v| HashableValue(v.normalize())).collect();
+        assert_eq!(set.len(), 1);
+    }
+}
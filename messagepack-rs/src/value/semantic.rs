@@ -0,0 +1,151 @@
+use super::Value;
+use std::convert::TryFrom;
+
+/// How `Value::semantic_eq` compares floats, after widening `Float32` to `f64`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FloatEq {
+    /// Equal bit patterns, so NaNs with the same payload are equal and `0.0` differs from `-0.0`.
+    Bits,
+    /// Equal bit patterns or a difference of at most the given tolerance.
+    Tolerance(f64),
+}
+
+impl Value {
+    /// Replaces every integer, in arrays and maps too, with the narrowest variant that holds it:
+    /// unsigned when it is not negative and signed otherwise.
+    /// This is the variant that decoding the smallest encoding of the integer yields.
+    ///
+    /// A `FormattedTimestamp` also becomes a `Timestamp`, and a `NonUtf8String` whose bytes are valid UTF-8 becomes a `String`.
+    pub fn normalize(self) -> Self {
+        match self {
+            Value::Array(v) => Value::Array(v.into_iter().map(Value::normalize).collect()),
+            Value::Map(v) => Value::Map(v.into_iter().map(|(k, v)| (k, v.normalize())).collect()),
+            Value::FormattedTimestamp(v, _) => Value::Timestamp(v),
+            Value::NonUtf8String(v) => match String::from_utf8(v) {
+                Ok(v) => Value::String(v),
+                Err(e) => Value::NonUtf8String(e.into_bytes()),
+            },
+            v => match v.integer() {
+                Some(n) => narrowest(n),
+                None => v,
+            },
+        }
+    }
+
+    /// Compares integers by value regardless of their variants and floats as `floats` says, recursing into arrays and maps.
+    /// Integers and floats are never equal to each other.
+    /// Timestamps are compared regardless of their format and strings by their bytes, whether or not they are valid UTF-8,
+    /// which agrees with comparing normalized values; every other variant is compared with `==`.
+    pub fn semantic_eq(&self, other: &Self, floats: FloatEq) -> bool {
+        match (self, other) {
+            (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.semantic_eq(b, floats)),
+            (Value::Map(a), Value::Map(b)) => a.len() == b.len() && a.iter().zip(b).all(|((ka, va), (kb, vb))| ka == kb && va.semantic_eq(vb, floats)),
+            _ => match (self.integer(), other.integer(), self.float(), other.float()) {
+                (Some(a), Some(b), _, _) => a == b,
+                (_, _, Some(a), Some(b)) => a.to_bits() == b.to_bits() || matches!(floats, FloatEq::Tolerance(tolerance) if (a - b).abs() <= tolerance),
+                (None, None, None, None) => match (self, other) {
+                    (Value::Timestamp(a) | Value::FormattedTimestamp(a, _), Value::Timestamp(b) | Value::FormattedTimestamp(b, _)) => a == b,
+                    (Value::String(a), Value::NonUtf8String(b)) | (Value::NonUtf8String(b), Value::String(a)) => a.as_bytes() == &b[..],
+                    _ => self == other,
+                },
+                _ => false,
+            },
+        }
+    }
+
+    /// The value of any integer variant.
+    pub(crate) fn integer(&self) -> Option<i128> {
+        match *self {
+            Value::UInt8(v) => Some(i128::from(v)),
+            Value::UInt16(v) => Some(i128::from(v)),
+            Value::UInt32(v) => Some(i128::from(v)),
+            Value::UInt64(v) => Some(i128::from(v)),
+            Value::Int8(v) => Some(i128::from(v)),
+            Value::Int16(v) => Some(i128::from(v)),
+            Value::Int32(v) => Some(i128::from(v)),
+            Value::Int64(v) => Some(i128::from(v)),
+            _ => None,
+        }
+    }
+
+    /// The value of either float variant, widened to `f64`.
+    pub(crate) fn float(&self) -> Option<f64> {
+        match *self {
+            Value::Float32(v) => Some(f64::from(v)),
+            Value::Float64(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// `v` always comes from an integer variant, so it fits in `u64` or `i64`.
+fn narrowest(v: i128) -> Value {
+    if let Ok(v) = u8::try_from(v) {
+        Value::UInt8(v)
+    } else if let Ok(v) = u16::try_from(v) {
+        Value::UInt16(v)
+    } else if let Ok(v) = u32::try_from(v) {
+        Value::UInt32(v)
+    } else if let Ok(v) = u64::try_from(v) {
+        Value::UInt64(v)
+    } else if let Ok(v) = i8::try_from(v) {
+        Value::Int8(v)
+    } else if let Ok(v) = i16::try_from(v) {
+        Value::Int16(v)
+    } else if let Ok(v) = i32::try_from(v) {
+        Value::Int32(v)
+    } else {
+        Value::Int64(v as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::{Timestamp, TimestampFormat};
+
+    #[test]
+    fn normalize_narrows_integers_at_any_depth() {
+        let value = Value::Array(vec![Value::Int64(1), Value::UInt32(300), Value::Int32(-1), Value::Int64(-129), Value::UInt64(u64::MAX)]);
+        assert_eq!(value.normalize(), Value::Array(vec![Value::UInt8(1), Value::UInt16(300), Value::Int8(-1), Value::Int16(-129), Value::UInt64(u64::MAX)]));
+        let value: Value = vec![("a", Value::Int16(7))].into_iter().collect();
+        assert_eq!(value.normalize(), vec![("a", Value::UInt8(7))].into_iter().collect());
+    }
+
+    #[test]
+    fn normalize_drops_timestamp_formats_and_recovers_utf8() {
+        let timestamp = Timestamp::new(1, 0);
+        assert_eq!(Value::FormattedTimestamp(timestamp, TimestampFormat::Timestamp96).normalize(), Value::Timestamp(timestamp));
+        assert_eq!(Value::NonUtf8String(b"ok".to_vec()).normalize(), Value::String(String::from("ok")));
+        assert_eq!(Value::NonUtf8String(vec![0xe9]).normalize(), Value::NonUtf8String(vec![0xe9]));
+    }
+
+    #[test]
+    fn semantic_eq_compares_integers_by_value() {
+        assert!(Value::UInt8(1).semantic_eq(&Value::Int64(1), FloatEq::Bits));
+        assert!(Value::Array(vec![Value::UInt32(1)]).semantic_eq(&Value::Array(vec![Value::Int8(1)]), FloatEq::Bits));
+        assert!(!Value::UInt8(1).semantic_eq(&Value::UInt8(2), FloatEq::Bits));
+        assert!(!Value::UInt8(1).semantic_eq(&Value::Float64(1.0), FloatEq::Tolerance(0.5)));
+        assert!(!Value::Array(vec![Value::UInt8(1)]).semantic_eq(&Value::Array(vec![]), FloatEq::Bits));
+    }
+
+    #[test]
+    fn semantic_eq_compares_floats_by_bits_or_tolerance() {
+        assert!(Value::Float32(0.5).semantic_eq(&Value::Float64(0.5), FloatEq::Bits));
+        assert!(Value::Float64(f64::NAN).semantic_eq(&Value::Float64(f64::NAN), FloatEq::Bits));
+        assert!(!Value::Float64(0.0).semantic_eq(&Value::Float64(-0.0), FloatEq::Bits));
+        assert!(Value::Float64(0.0).semantic_eq(&Value::Float64(-0.0), FloatEq::Tolerance(0.0)));
+        assert!(!Value::Float64(1.0).semantic_eq(&Value::Float64(1.1), FloatEq::Bits));
+        assert!(Value::Float64(1.0).semantic_eq(&Value::Float64(1.1), FloatEq::Tolerance(0.2)));
+    }
+
+    #[test]
+    fn semantic_eq_ignores_timestamp_formats_and_string_representations() {
+        let timestamp = Timestamp::new(1, 0);
+        assert!(Value::Timestamp(timestamp).semantic_eq(&Value::FormattedTimestamp(timestamp, TimestampFormat::Timestamp64), FloatEq::Bits));
+        assert!(Value::FormattedTimestamp(timestamp, TimestampFormat::Timestamp32).semantic_eq(&Value::FormattedTimestamp(timestamp, TimestampFormat::Timestamp96), FloatEq::Bits));
+        assert!(!Value::Timestamp(timestamp).semantic_eq(&Value::Timestamp(Timestamp::new(2, 0)), FloatEq::Bits));
+        assert!(Value::NonUtf8String(b"ok".to_vec()).semantic_eq(&Value::from("ok"), FloatEq::Bits));
+        assert!(!Value::from("ok").semantic_eq(&Value::NonUtf8String(vec![0xe9]), FloatEq::Bits));
+    }
+}
@@ -0,0 +1,239 @@
+use crate::binary::Binary;
+use crate::deserializable::DeserializeError;
+use crate::extension::Extension;
+use crate::reader::{Header, InvalidUtf8, Reader, MAX_PREALLOCATED};
+use crate::timestamp::{Timestamp, TimestampFormat};
+use crate::value::Value;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::Read;
+
+/// A type built from MessagePack through callbacks, one per type of value.
+///
+/// Unlike `Deserializable`, which requires a conversion from every MessagePack type, a target implements only the callbacks it accepts.
+/// Every other callback fails with `InvalidMarker`.
+/// Signed integers that are not negative fall back to `visit_uint` and `float 32` falls back to `visit_f64`.
+/// A timestamp extension goes to `visit_timestamp`, or to `visit_formatted_timestamp` when the reader was created
+/// with `preserve_timestamp_format`, or to `visit_ext` when its data is malformed and the reader was created with `lenient_timestamps`.
+pub trait Visitor: Sized {
+    fn visit<R: Read>(reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        let header = reader.read_header()?;
+        Self::visit_with_header(header, reader)
+    }
+
+    /// Dispatches the value whose header has already been read to its callback.
+    fn visit_with_header<R: Read>(header: Header, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        match header {
+            Header::Nil => Self::visit_nil(),
+            Header::Bool(v) => Self::visit_bool(v),
+            Header::UInt(v) => Self::visit_uint(v),
+            Header::Int(v) => Self::visit_int(v),
+            Header::Float32(v) => Self::visit_f32(v),
+            Header::Float64(v) => Self::visit_f64(v),
+            Header::Str(size) => {
+                let mut buf = Vec::new();
+                reader.read_payload(size, &mut buf)?;
+                if reader.options().str_as_binary {
+                    return Self::visit_bin(buf);
+                }
+                match String::from_utf8(buf) {
+                    Ok(v) => Self::visit_str(v),
+                    Err(e) => match reader.options().invalid_utf8 {
+                        InvalidUtf8::Reject => Err(DeserializeError::InvalidValue),
+                        InvalidUtf8::Keep => Self::visit_non_utf8_str(e.into_bytes()),
+                        InvalidUtf8::Lossy => Self::visit_str(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+                        InvalidUtf8::Binary => Self::visit_bin(e.into_bytes()),
+                    },
+                }
+            },
+            Header::Bin(size) => {
+                let mut buf = Vec::new();
+                reader.read_payload(size, &mut buf)?;
+                Self::visit_bin(buf)
+            },
+            Header::Ext(t, size) => {
+                let mut data = Vec::new();
+                reader.read_payload(size, &mut data)?;
+                if t != Timestamp::EXTENSION_TYPE {
+                    return Self::visit_ext(t, data);
+                }
+                match Timestamp::from_extension_data(&data) {
+                    Ok(v) => match TimestampFormat::from_data_len(data.len()) {
+                        Some(format) if reader.options().preserve_timestamp_format => Self::visit_formatted_timestamp(v, format),
+                        _ => Self::visit_timestamp(v),
+                    },
+                    Err(_) if reader.options().lenient_timestamps => Self::visit_ext(t, data),
+                    Err(e) => Err(e),
+                }
+            },
+            Header::Array(len) => Self::visit_array(len, reader),
+            Header::Map(len) => Self::visit_map(len, reader),
+        }
+    }
+
+    fn visit_nil() -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidMarker)
+    }
+
+    fn visit_bool(_: bool) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidMarker)
+    }
+
+    fn visit_uint(_: u64) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidMarker)
+    }
+
+    fn visit_int(v: i64) -> Result<Self, DeserializeError> {
+        Self::visit_uint(u64::try_from(v).or(Err(DeserializeError::InvalidMarker))?)
+    }
+
+    fn visit_f32(v: f32) -> Result<Self, DeserializeError> {
+        Self::visit_f64(f64::from(v))
+    }
+
+    fn visit_f64(_: f64) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidMarker)
+    }
+
+    fn visit_str(_: String) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidMarker)
+    }
+
+    /// Called for a str that is not valid UTF-8 when the reader was created with `InvalidUtf8::Keep`.
+    fn visit_non_utf8_str(_: Vec<u8>) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidValue)
+    }
+
+    fn visit_bin(_: Vec<u8>) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidMarker)
+    }
+
+    /// Called with the `len` elements still unread; the implementation must consume all of them.
+    fn visit_array<R: Read>(_len: usize, _reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidMarker)
+    }
+
+    /// Called with the `len` key-value pairs still unread; the implementation must consume all of them.
+    fn visit_map<R: Read>(_len: usize, _reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidMarker)
+    }
+
+    fn visit_ext(_: i8, _: Vec<u8>) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidMarker)
+    }
+
+    fn visit_timestamp(_: Timestamp) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::InvalidMarker)
+    }
+
+    fn visit_formatted_timestamp(v: Timestamp, _: TimestampFormat) -> Result<Self, DeserializeError> {
+        Self::visit_timestamp(v)
+    }
+}
+
+/// Reads a map key of `Value`. Keys can only be strings, so a key that is not valid UTF-8 is converted only with `InvalidUtf8::Lossy`
+/// and fails with `InvalidValue` in every other mode, rather than being re-encoded to different bytes.
+pub(crate) fn map_key<R: Read>(reader: &mut Reader<R>) -> Result<String, DeserializeError> {
+    let size = reader.read_str_len()?;
+    let mut buf = Vec::new();
+    reader.read_payload(size, &mut buf)?;
+    match String::from_utf8(buf) {
+        Ok(v) => Ok(v),
+        Err(e) if reader.options().invalid_utf8 == InvalidUtf8::Lossy => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        Err(_) => Err(DeserializeError::InvalidValue),
+    }
+}
+
+/// Integers are given the narrowest variant of their signedness that holds them,
+/// which matches `Deserializable` for minimally encoded input.
+impl Visitor for Value {
+    fn visit_nil() -> Result<Self, DeserializeError> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_bool(v: bool) -> Result<Self, DeserializeError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_uint(v: u64) -> Result<Self, DeserializeError> {
+        Ok(if let Ok(v) = u8::try_from(v) {
+            Value::UInt8(v)
+        } else if let Ok(v) = u16::try_from(v) {
+            Value::UInt16(v)
+        } else if let Ok(v) = u32::try_from(v) {
+            Value::UInt32(v)
+        } else {
+            Value::UInt64(v)
+        })
+    }
+
+    fn visit_int(v: i64) -> Result<Self, DeserializeError> {
+        Ok(if let Ok(v) = i8::try_from(v) {
+            Value::Int8(v)
+        } else if let Ok(v) = i16::try_from(v) {
+            Value::Int16(v)
+        } else if let Ok(v) = i32::try_from(v) {
+            Value::Int32(v)
+        } else {
+            Value::Int64(v)
+        })
+    }
+
+    fn visit_f32(v: f32) -> Result<Self, DeserializeError> {
+        Ok(Value::Float32(v))
+    }
+
+    fn visit_f64(v: f64) -> Result<Self, DeserializeError> {
+        Ok(Value::Float64(v))
+    }
+
+    fn visit_str(v: String) -> Result<Self, DeserializeError> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_non_utf8_str(v: Vec<u8>) -> Result<Self, DeserializeError> {
+        Ok(Value::NonUtf8String(v))
+    }
+
+    fn visit_bin(v: Vec<u8>) -> Result<Self, DeserializeError> {
+        Ok(Value::Binary(Binary(v)))
+    }
+
+    fn visit_array<R: Read>(len: usize, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        let mut buf = Vec::with_capacity(len.min(MAX_PREALLOCATED));
+        for _ in 0..len {
+            buf.push(Self::visit(reader)?);
+        }
+        Ok(Value::Array(buf))
+    }
+
+    fn visit_map<R: Read>(len: usize, reader: &mut Reader<R>) -> Result<Self, DeserializeError> {
+        let mut buf = BTreeMap::new();
+        for _ in 0..len {
+            buf.insert(map_key(reader)?, Self::visit(reader)?);
+        }
+        Ok(Value::Map(buf))
+    }
+
+    fn visit_ext(t: i8, data: Vec<u8>) -> Result<Self, DeserializeError> {
+        Ok(Value::Extension(Extension { t, data }))
+    }
+
+    fn visit_timestamp(v: Timestamp) -> Result<Self, DeserializeError> {
+        Ok(Value::Timestamp(v))
+    }
+
+    fn visit_formatted_timestamp(v: Timestamp, format: TimestampFormat) -> Result<Self, DeserializeError> {
+        Ok(Value::FormattedTimestamp(v, format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forged_array_length_fails() {
+        assert!(Value::visit(&mut Reader::new(&[0xdd, 0xff, 0xff, 0xff, 0xff, 0xc0][..])).is_err());
+    }
+}
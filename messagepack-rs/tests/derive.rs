@@ -0,0 +1,107 @@
+use messagepack_rs::binary::Binary;
+use messagepack_rs::deserializable::Deserializable;
+use messagepack_rs::extension::Extension;
+use messagepack_rs::serializable::Serializable;
+use messagepack_rs::timestamp::Timestamp;
+use messagepack_rs::value::Value;
+use messagepack_rs_macros::{MessagePackExtension, MessagePackFrom, MessagePackValue};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Clone, Debug, PartialEq, MessagePackExtension)]
+#[msgpack_ext(type = 0)]
+struct Rgba {
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, MessagePackFrom, MessagePackValue)]
+enum MyValue {
+    Nil,
+    Bool(bool),
+    Float32(f32),
+    Float64(f64),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    Binary(Binary),
+    String(String),
+    Array(Vec<Self>),
+    Map(BTreeMap<String, Self>),
+    Extension(Extension),
+    Timestamp(Timestamp),
+    #[msgpack_ext(type = 0)]
+    Rgba(Rgba),
+}
+
+#[test]
+fn extra_variant_round_trips() {
+    let value = MyValue::Rgba(Rgba { r: 5, g: 10, b: 15, a: 20 });
+    let serialized = value.clone().serialize().unwrap();
+    assert_eq!(serialized, [0xd6, 0x00, 0x05, 0x0a, 0x0f, 0x14]);
+    assert_eq!(MyValue::deserialize(&mut &serialized[..]).unwrap(), value);
+}
+
+#[test]
+fn malformed_extra_variant_payload_stays_an_extension() {
+    let malformed = Extension { t: 0, data: vec![1, 2, 3] };
+    let buf = [0x92, 0xc7, 0x03, 0x00, 0x01, 0x02, 0x03, 0xc3];
+    let expected = MyValue::Array(vec![MyValue::Extension(malformed.clone()), MyValue::Bool(true)]);
+    assert_eq!(MyValue::deserialize(&mut &buf[..]).unwrap(), expected);
+    assert_eq!(MyValue::from(Value::deserialize(&mut &buf[..]).unwrap()), expected);
+}
+
+#[test]
+fn forged_extra_variant_length_fails() {
+    let buf = [0xc9, 0xff, 0xff, 0xff, 0xff, 0x00, 0x01];
+    assert!(MyValue::deserialize(&mut &buf[..]).is_err());
+}
+
+/// A generic enum, with renamed variants and a `HashMap` map, exercises the generics the conversions add.
+#[derive(Clone, Debug, PartialEq, MessagePackFrom)]
+enum G<X: Clone> {
+    Nil,
+    Bool(bool),
+    Float32(f32),
+    Float64(f64),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    Binary(Binary),
+    #[msgpack(string)]
+    Text(String),
+    #[msgpack(array)]
+    List(Vec<G<X>>),
+    #[msgpack(map)]
+    Entries(HashMap<String, Self>),
+    Extension(Extension),
+    Timestamp(Timestamp),
+    Other(X),
+}
+
+#[test]
+fn generic_enum_conversions() {
+    assert_eq!(G::<u8>::from(vec![1u8, 2]), G::List(vec![G::UInt8(1), G::UInt8(2)]));
+    assert_eq!(G::<u8>::from(&[G::Nil, G::Other(3)][..]), G::List(vec![G::Nil, G::Other(3)]));
+    assert_eq!(G::<u8>::from(None::<u16>), G::Nil);
+    assert_eq!(G::<u8>::from(Some("a")), G::Text(String::from("a")));
+
+    let entries: HashMap<_, _> = vec![(String::from("k"), G::Other(4))].into_iter().collect();
+    let mut map = BTreeMap::new();
+    map.insert("k", G::Other(4));
+    assert_eq!(G::<u8>::from(map), G::Entries(entries));
+    let mut map = HashMap::new();
+    map.insert("k", 1u32);
+    assert_eq!(G::<u8>::from(map), G::Entries(vec![(String::from("k"), G::UInt32(1))].into_iter().collect()));
+}